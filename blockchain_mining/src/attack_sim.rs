@@ -0,0 +1,336 @@
+use crate::block_tree::{BlockTree, InsertOutcome};
+use crate::target::{to_hex, Target};
+use crate::Block;
+use rand::Rng;
+use std::collections::HashSet;
+
+const ATTACKER: &str = "Attacker";
+const HONEST: &str = "Honest";
+
+// Mine a block on `parent`. As in the network simulation the event model
+// decides who finds blocks, so the proof of work is real but trivial.
+fn mine_on(parent: &Block, miner: &str, count: &mut u64) -> Block {
+    *count += 1;
+    let data = format!("{} block #{}", miner, count);
+    let mut block = Block::new(parent.index + 1, data, parent.hash.clone(), Target::max());
+    loop {
+        let hash_bytes = block.calculate_hash();
+        if block.target.is_met_by(&hash_bytes) {
+            block.hash = to_hex(&hash_bytes);
+            return block;
+        }
+        block.nonce += 1;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SelfishMiningReport {
+    pub attacker_blocks: usize,
+    pub honest_blocks: usize,
+}
+
+impl SelfishMiningReport {
+    pub fn revenue_share(&self) -> f64 {
+        self.attacker_blocks as f64 / (self.attacker_blocks + self.honest_blocks).max(1) as f64
+    }
+}
+
+// Eyal & Sirer's selfish mining strategy. `alpha` is the attacker's share of
+// hash power and `gamma` the fraction of honest miners that build on the
+// attacker's block when two blocks race at the same height.
+pub fn selfish_mining(genesis: &Block, alpha: f64, gamma: f64, blocks: usize) -> SelfishMiningReport {
+    selfish_mining_with(genesis, alpha, gamma, blocks, &mut rand::thread_rng())
+}
+
+// Same, deciding who finds each block with `rng` so a seeded run repeats
+pub fn selfish_mining_with(genesis: &Block, alpha: f64, gamma: f64, blocks: usize,
+                           rng: &mut impl Rng) -> SelfishMiningReport {
+    let mut count = 0;
+    // Hashes of every block the attacker mined, published or not
+    let mut attacker_mined = HashSet::new();
+    let mut public = BlockTree::new(genesis.clone());
+    // The attacker's tip and the blocks on it nobody else has seen yet
+    let mut attacker_tip = genesis.clone();
+    let mut withheld: Vec<Block> = Vec::new();
+    // Honest block racing the attacker's just-published block at the same height
+    let mut race: Option<Block> = None;
+
+    let publish = |public: &mut BlockTree, withheld: &mut Vec<Block>, n: usize| {
+        for block in withheld.drain(..n) {
+            public.insert(block).expect("attacker block is valid");
+        }
+    };
+
+    for _ in 0..blocks {
+        if rng.gen::<f64>() < alpha {
+            let block = mine_on(&attacker_tip, ATTACKER, &mut count);
+            attacker_mined.insert(block.hash.clone());
+            attacker_tip = block.clone();
+            withheld.push(block);
+
+            // Winning a race: publish right away to settle it
+            if race.take().is_some() {
+                let n = withheld.len();
+                publish(&mut public, &mut withheld, n);
+            }
+            continue;
+        }
+
+        let parent = match &race {
+            Some(honest_tip) if rng.gen::<f64>() >= gamma => honest_tip.clone(),
+            Some(_) => attacker_tip.clone(),
+            None => public.tip().block.clone(),
+        };
+        let lead = attacker_tip.index as i64 - public.tip().height as i64;
+        let block = mine_on(&parent, HONEST, &mut count);
+        public.insert(block.clone()).expect("honest block is valid");
+
+        if race.take().is_some() {
+            // Whoever won, the attacker starts again on the public tip
+            attacker_tip = public.tip().block.clone();
+            continue;
+        }
+
+        match lead {
+            // Nothing withheld: adopt the honest block
+            i64::MIN..=0 => {
+                withheld.clear();
+                attacker_tip = public.tip().block.clone();
+            }
+            // Publish the one withheld block and race the honest one
+            1 => {
+                let n = withheld.len();
+                publish(&mut public, &mut withheld, n);
+                race = Some(block);
+            }
+            // Publishing everything overtakes the honest chain
+            2 => {
+                let n = withheld.len();
+                publish(&mut public, &mut withheld, n);
+            }
+            // Comfortably ahead: reveal just enough to match the honest height
+            _ => {
+                let public_height = public.tip().height;
+                let n = withheld.iter().take_while(|b| b.index <= public_height).count();
+                publish(&mut public, &mut withheld, n);
+            }
+        }
+    }
+
+    // Release whatever is left; it only wins if it has more work
+    let n = withheld.len();
+    publish(&mut public, &mut withheld, n);
+
+    let best = public.best_chain();
+    let attacker_blocks = best.iter().skip(1).filter(|b| attacker_mined.contains(&b.hash)).count();
+    SelfishMiningReport {
+        attacker_blocks,
+        honest_blocks: best.len() - 1 - attacker_blocks,
+    }
+}
+
+// Closed-form revenue share from Eyal & Sirer, "Majority is not Enough"
+pub fn selfish_mining_expected_share(alpha: f64, gamma: f64) -> f64 {
+    let numerator = alpha * (1.0 - alpha).powi(2) * (4.0 * alpha + gamma * (1.0 - 2.0 * alpha))
+        - alpha.powi(3);
+    let denominator = 1.0 - alpha * (1.0 + (2.0 - alpha) * alpha);
+    numerator / denominator
+}
+
+// Monte Carlo double spend: the attacker secretly mines a conflicting chain
+// from the block before the payment and wins if it ever catches up once the
+// merchant has seen `confirmations` blocks. Gives up `max_deficit` behind.
+pub fn double_spend_probability(q: f64, confirmations: u64, trials: u32, max_deficit: i64) -> f64 {
+    double_spend_probability_with(q, confirmations, trials, max_deficit, &mut rand::thread_rng())
+}
+
+pub fn double_spend_probability_with(q: f64, confirmations: u64, trials: u32, max_deficit: i64,
+                                     rng: &mut impl Rng) -> f64 {
+    let mut successes = 0;
+
+    for _ in 0..trials {
+        let (mut honest, mut attacker) = (0i64, 0i64);
+        loop {
+            if rng.gen::<f64>() < q {
+                attacker += 1;
+            } else {
+                honest += 1;
+            }
+
+            if honest >= confirmations as i64 && attacker >= honest {
+                successes += 1;
+                break;
+            }
+            if honest - attacker > max_deficit {
+                break;
+            }
+        }
+    }
+
+    successes as f64 / trials as f64
+}
+
+// Nakamoto's estimate from section 11 of the Bitcoin paper
+pub fn double_spend_expected(q: f64, confirmations: u64) -> f64 {
+    let p = 1.0 - q;
+    if q >= p {
+        return 1.0;
+    }
+
+    let z = confirmations as i32;
+    let lambda = z as f64 * q / p;
+    let mut sum = 1.0;
+    let mut poisson = (-lambda).exp();
+    for k in 0..=z {
+        if k > 0 {
+            poisson *= lambda / k as f64;
+        }
+        sum -= poisson * (1.0 - (q / p).powi(z - k));
+    }
+    sum
+}
+
+#[derive(Debug, Clone)]
+pub struct RewriteReport {
+    pub attacker_blocks: u64,
+    pub honest_blocks: u64,
+    pub reorg_depth: u64,
+}
+
+// A majority attacker forks `depth` blocks below the tip and mines in private
+// until its branch has more work, then publishes it all at once
+pub fn rewrite_last_blocks(genesis: &Block, q: f64, depth: u64) -> RewriteReport {
+    let mut rng = rand::thread_rng();
+    let mut count = 0;
+    let mut public = BlockTree::new(genesis.clone());
+
+    for _ in 0..depth {
+        let block = mine_on(&public.tip().block, HONEST, &mut count);
+        public.insert(block).expect("honest block is valid");
+    }
+
+    let mut attacker_tip = genesis.clone();
+    let mut withheld = Vec::new();
+    let mut honest_blocks = 0;
+    while attacker_tip.index <= public.tip().height {
+        if rng.gen::<f64>() < q {
+            attacker_tip = mine_on(&attacker_tip, ATTACKER, &mut count);
+            withheld.push(attacker_tip.clone());
+        } else {
+            let block = mine_on(&public.tip().block, HONEST, &mut count);
+            public.insert(block).expect("honest block is valid");
+            honest_blocks += 1;
+        }
+    }
+
+    let attacker_blocks = withheld.len() as u64;
+    let mut reorg_depth = 0;
+    for block in withheld {
+        if let InsertOutcome::Reorg { depth } = public.insert(block).expect("attacker block is valid") {
+            reorg_depth = depth;
+        }
+    }
+
+    RewriteReport { attacker_blocks, honest_blocks, reorg_depth }
+}
+
+pub fn display_attacks(genesis: &Block) {
+    println!("\n😈 SELFISH MINING: attacker revenue share (theory in parentheses)");
+    println!("───────────────────────────");
+    let gammas = [0.0, 0.5, 1.0];
+    print!("{:>8}", "alpha");
+    for gamma in &gammas {
+        print!("{:>20}", format!("gamma={}", gamma));
+    }
+    println!();
+    for alpha in [0.1, 0.2, 0.25, 0.3, 0.33, 0.4, 0.45] {
+        print!("{:>8}", format!("{:.0}%", alpha * 100.0));
+        for gamma in &gammas {
+            let report = selfish_mining(genesis, alpha, *gamma, 5_000);
+            let expected = selfish_mining_expected_share(alpha, *gamma);
+            print!("{:>20}", format!("{:.1}% ({:.1}%)", report.revenue_share() * 100.0, expected * 100.0));
+        }
+        println!();
+    }
+    println!("Selfish mining pays off when the share beats alpha itself.");
+
+    println!("\n💸 DOUBLE SPEND SUCCESS by confirmations (Nakamoto's estimate in parentheses)");
+    println!("───────────────────────────");
+    let depths = [1, 2, 3, 6, 10];
+    print!("{:>8}", "q");
+    for z in &depths {
+        print!("{:>18}", format!("z={}", z));
+    }
+    println!();
+    for q in [0.1, 0.2, 0.3, 0.4, 0.45] {
+        print!("{:>8}", format!("{:.0}%", q * 100.0));
+        for z in &depths {
+            let measured = double_spend_probability(q, *z, 20_000, 50);
+            let expected = double_spend_expected(q, *z);
+            print!("{:>18}", format!("{:.2}% ({:.2}%)", measured * 100.0, expected * 100.0));
+        }
+        println!();
+    }
+
+    println!("\n🏴 MAJORITY ATTACK: rewriting the last N blocks (average of 20 runs)");
+    println!("───────────────────────────");
+    for q in [0.51, 0.6, 0.75] {
+        for depth in [1, 3, 6, 10] {
+            let runs: Vec<RewriteReport> = (0..20).map(|_| rewrite_last_blocks(genesis, q, depth)).collect();
+            let avg = |f: fn(&RewriteReport) -> u64| runs.iter().map(f).sum::<u64>() as f64 / runs.len() as f64;
+            println!("   {:.0}% hash power, N={:>2}: {:>6.1} attacker blocks, {:>6.1} honest blocks meanwhile, reorg depth {:.1}",
+                     q * 100.0, depth, avg(|r| r.attacker_blocks), avg(|r| r.honest_blocks), avg(|r| r.reorg_depth));
+        }
+    }
+    println!("───────────────────────────\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pow::PowAlgorithm;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn selfish_mining_revenue_tracks_eyal_and_sirer() {
+        let genesis = Block::genesis(PowAlgorithm::default(), &mut ());
+        for (alpha, gamma) in [(0.1, 0.0), (0.25, 0.5), (0.3, 0.0), (0.35, 1.0), (0.4, 0.0)] {
+            let report = selfish_mining_with(&genesis, alpha, gamma, 10_000, &mut StdRng::seed_from_u64(1));
+            let expected = selfish_mining_expected_share(alpha, gamma);
+            assert!((report.revenue_share() - expected).abs() < 0.025,
+                    "alpha {} gamma {}: {:.4} vs {:.4}", alpha, gamma, report.revenue_share(), expected);
+        }
+        // Below the threshold it loses money, above it it pays
+        assert!(selfish_mining_expected_share(0.1, 0.0) < 0.1);
+        assert!(selfish_mining_expected_share(0.4, 0.0) > 0.4);
+    }
+
+    // Section 11 of the Bitcoin paper: (q, z, P) for z > 0
+    const NAKAMOTO_TABLE: [(f64, u64, f64); 13] = [
+        (0.1, 1, 0.2045873), (0.1, 2, 0.0509779), (0.1, 3, 0.0131722), (0.1, 4, 0.0034552),
+        (0.1, 5, 0.0009137), (0.1, 6, 0.0002428), (0.1, 7, 0.0000647), (0.1, 8, 0.0000173),
+        (0.1, 9, 0.0000046), (0.1, 10, 0.0000012),
+        (0.3, 5, 0.1773523), (0.3, 10, 0.0416605), (0.3, 15, 0.0101008),
+    ];
+
+    #[test]
+    fn double_spend_estimate_reproduces_nakamotos_table() {
+        for (q, z, p) in NAKAMOTO_TABLE {
+            assert!((double_spend_expected(q, z) - p).abs() < 1e-7, "q {} z {}", q, z);
+        }
+        assert_eq!(double_spend_expected(0.5, 6), 1.0);
+    }
+
+    // Nakamoto approximates the attacker's progress as Poisson, which
+    // understates deep attacks a little, so the simulated race only has to
+    // land close to his numbers
+    #[test]
+    fn simulated_double_spends_land_near_nakamotos_table() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for (q, z, p) in NAKAMOTO_TABLE {
+            let measured = double_spend_probability_with(q, z, 10_000, 20, &mut rng);
+            assert!((measured - p).abs() < 0.03, "q {} z {}: {:.5} vs {:.5}", q, z, measured, p);
+        }
+    }
+}
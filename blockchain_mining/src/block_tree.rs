@@ -0,0 +1,286 @@
+use crate::error::{BlockchainError, Result};
+use crate::Block;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone)]
+pub struct TreeNode {
+    pub block: Block,
+    pub height: u64,
+    // Sum of expected hashes (target difficulty) from genesis up to this block
+    pub chain_work: f64,
+}
+
+// What inserting a block did to the node's view of the best chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InsertOutcome {
+    // The block extends the current best tip
+    Extended,
+    // Stored on a side branch with no more work than the best chain
+    SideChain,
+    // A side branch overtook the best chain; `depth` blocks were disconnected
+    Reorg { depth: u64 },
+    // Already known
+    Duplicate,
+}
+
+// Every block a node has heard of, keyed by hash. The best chain is the
+// branch ending at the tip with the most cumulative work.
+//
+// Saved as a list of blocks: the best chain first, then the side branches,
+// parents before children. Loading inserts them in that order, so ties still
+// go to the tip we had.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "SavedTree", into = "SavedTree")]
+pub struct BlockTree {
+    nodes: HashMap<String, TreeNode>,
+    best_tip: String,
+}
+
+impl BlockTree {
+    pub fn new(genesis: Block) -> Self {
+        let hash = genesis.hash.clone();
+        let node = TreeNode {
+            chain_work: genesis.target.difficulty(),
+            block: genesis,
+            height: 0,
+        };
+
+        let mut nodes = HashMap::new();
+        nodes.insert(hash.clone(), node);
+        BlockTree { nodes, best_tip: hash }
+    }
+
+    pub fn tip(&self) -> &TreeNode {
+        &self.nodes[&self.best_tip]
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.nodes.contains_key(hash)
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn insert(&mut self, block: Block) -> Result<InsertOutcome> {
+        if self.nodes.contains_key(&block.hash) {
+            return Ok(InsertOutcome::Duplicate);
+        }
+
+        let parent = self.nodes.get(&block.previous_hash).ok_or_else(|| {
+            BlockchainError::InvalidBlock {
+                index: block.index,
+                message: format!("unknown parent {}", block.previous_hash),
+            }
+        })?;
+
+        if block.index != parent.height + 1 {
+            return Err(BlockchainError::InvalidIndex {
+                expected: parent.height + 1,
+                found: block.index,
+            });
+        }
+        // Parameters that would overflow or exhaust memory never get hashed
+        block.pow.check().map_err(|message| BlockchainError::InvalidPow { algorithm: block.pow, message })?;
+        block.verify_pow()?;
+
+        let extends_tip = block.previous_hash == self.best_tip;
+        let node = TreeNode {
+            height: parent.height + 1,
+            chain_work: parent.chain_work + block.target.difficulty(),
+            block,
+        };
+        let hash = node.block.hash.clone();
+        let chain_work = node.chain_work;
+        self.nodes.insert(hash.clone(), node);
+
+        // Ties go to the tip we saw first
+        if chain_work <= self.tip().chain_work {
+            return Ok(InsertOutcome::SideChain);
+        }
+
+        let old_tip = std::mem::replace(&mut self.best_tip, hash);
+        if extends_tip {
+            Ok(InsertOutcome::Extended)
+        } else {
+            let fork_point = self.common_ancestor(&old_tip, &self.best_tip);
+            let depth = self.nodes[&old_tip].height - self.nodes[&fork_point].height;
+            Ok(InsertOutcome::Reorg { depth })
+        }
+    }
+
+    fn common_ancestor(&self, a: &str, b: &str) -> String {
+        let mut a = &self.nodes[a];
+        let mut b = &self.nodes[b];
+
+        while a.height > b.height {
+            a = &self.nodes[&a.block.previous_hash];
+        }
+        while b.height > a.height {
+            b = &self.nodes[&b.block.previous_hash];
+        }
+        while a.block.hash != b.block.hash {
+            a = &self.nodes[&a.block.previous_hash];
+            b = &self.nodes[&b.block.previous_hash];
+        }
+        a.block.hash.clone()
+    }
+
+    // Blocks of the best chain, genesis first
+    pub fn best_chain(&self) -> Vec<&Block> {
+        self.branch(&self.best_tip)
+    }
+
+    // Blocks from genesis up to and including `hash`; empty if it's unknown
+    pub fn branch(&self, hash: &str) -> Vec<&Block> {
+        let mut chain = Vec::new();
+        let Some(mut node) = self.nodes.get(hash) else {
+            return chain;
+        };
+        loop {
+            chain.push(&node.block);
+            match self.nodes.get(&node.block.previous_hash) {
+                Some(parent) if node.height > 0 => node = parent,
+                _ => break,
+            }
+        }
+        chain.reverse();
+        chain
+    }
+
+    // Blocks we know of that aren't on the best chain
+    pub fn orphans(&self) -> Vec<&Block> {
+        let best: HashSet<&str> = self.best_chain().iter().map(|b| b.hash.as_str()).collect();
+        self.nodes
+            .values()
+            .filter(|node| !best.contains(node.block.hash.as_str()))
+            .map(|node| &node.block)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+struct SavedTree(Vec<Block>);
+
+impl From<BlockTree> for SavedTree {
+    fn from(tree: BlockTree) -> Self {
+        let mut side_blocks: Vec<&Block> = tree.orphans();
+        side_blocks.sort_by(|a, b| (a.index, &a.hash).cmp(&(b.index, &b.hash)));
+        SavedTree(tree.best_chain().into_iter().chain(side_blocks).cloned().collect())
+    }
+}
+
+impl TryFrom<SavedTree> for BlockTree {
+    type Error = BlockchainError;
+
+    fn try_from(saved: SavedTree) -> Result<Self> {
+        let mut blocks = saved.0.into_iter();
+        let mut tree = BlockTree::new(blocks.next().ok_or(BlockchainError::EmptyChain)?);
+        for block in blocks {
+            tree.insert(block)?;
+        }
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{MiningLimits, MiningOutcome};
+    use crate::pow::PowAlgorithm;
+    use crate::target::Target;
+
+    fn genesis() -> Block {
+        Block::genesis(PowAlgorithm::default(), &mut ())
+    }
+
+    // The tree only cares about linkage and proof of work, so `target` sets
+    // how much work the block adds
+    fn mine_on(parent: &Block, data: &str, target: Target) -> Block {
+        let mut block = Block::new(parent.index + 1, data.to_string(), parent.hash.clone(), target);
+        assert!(matches!(block.mine(&MiningLimits::none(), &mut ()), MiningOutcome::Found(_)));
+        block
+    }
+
+    fn hashes(blocks: &[&Block]) -> HashSet<String> {
+        blocks.iter().map(|block| block.hash.clone()).collect()
+    }
+
+    // genesis - a1 - a2, with b1 and c1 on genesis each outweighing a1 + a2
+    fn forked_tree() -> (BlockTree, Vec<Block>) {
+        let genesis = genesis();
+        let a1 = mine_on(&genesis, "a1", Target::max());
+        let a2 = mine_on(&a1, "a2", Target::max());
+        let b1 = mine_on(&genesis, "b1", Target::from_leading_zeros(1));
+        let c1 = mine_on(&genesis, "c1", Target::from_leading_zeros(1));
+
+        let mut tree = BlockTree::new(genesis.clone());
+        assert_eq!(tree.insert(a1.clone()).unwrap(), InsertOutcome::Extended);
+        assert_eq!(tree.insert(a2.clone()).unwrap(), InsertOutcome::Extended);
+        assert_eq!(tree.insert(b1.clone()).unwrap(), InsertOutcome::Reorg { depth: 2 });
+        // Equal work: the tip we saw first stays
+        assert_eq!(tree.insert(c1.clone()).unwrap(), InsertOutcome::SideChain);
+        (tree, vec![genesis, a1, a2, b1, c1])
+    }
+
+    #[test]
+    fn follows_the_tip_with_the_most_work_across_reorgs() {
+        let (mut tree, blocks) = forked_tree();
+        let [genesis, a1, a2, b1, c1] = &blocks[..] else { unreachable!() };
+        assert_eq!(tree.tip().block.hash, b1.hash);
+        assert_eq!(hashes(&tree.best_chain()), hashes(&[genesis, b1]));
+
+        // One more heavy block puts the first branch back in front
+        let a3 = mine_on(a2, "a3", Target::from_leading_zeros(1));
+        assert_eq!(tree.insert(a3.clone()).unwrap(), InsertOutcome::Reorg { depth: 1 });
+        assert_eq!(tree.tip().block.hash, a3.hash);
+        assert_eq!(tree.tip().height, 3);
+        assert_eq!(hashes(&tree.best_chain()), hashes(&[genesis, a1, a2, &a3]));
+        assert_eq!(hashes(&tree.orphans()), hashes(&[b1, c1]));
+    }
+
+    #[test]
+    fn keeps_side_branches_and_refuses_blocks_it_cant_place() {
+        let (mut tree, blocks) = forked_tree();
+        let [genesis, a1, a2, _, c1] = &blocks[..] else { unreachable!() };
+        assert_eq!(tree.block_count(), 5);
+        assert_eq!(hashes(&tree.branch(&a2.hash)), hashes(&[genesis, a1, a2]));
+        assert_eq!(hashes(&tree.branch(&c1.hash)), hashes(&[genesis, c1]));
+        assert!(tree.branch("unknown").is_empty());
+        assert_eq!(tree.insert(a2.clone()).unwrap(), InsertOutcome::Duplicate);
+
+        let mut stray = Block::new(7, "stray".to_string(), "00".repeat(32), Target::max());
+        assert!(matches!(stray.mine(&MiningLimits::none(), &mut ()), MiningOutcome::Found(_)));
+        assert!(matches!(tree.insert(stray), Err(BlockchainError::InvalidBlock { index: 7, .. })));
+
+        let mut skipped = Block::new(5, "skipped".to_string(), a1.hash.clone(), Target::max());
+        assert!(matches!(skipped.mine(&MiningLimits::none(), &mut ()), MiningOutcome::Found(_)));
+        assert!(matches!(tree.insert(skipped), Err(BlockchainError::InvalidIndex { expected: 2, found: 5 })));
+
+        let mut forged = mine_on(a2, "a3", Target::max());
+        forged.data = "rewritten".to_string();
+        assert!(tree.insert(forged).is_err());
+        assert_eq!(tree.block_count(), 5);
+    }
+
+    #[test]
+    fn saved_trees_load_with_the_same_tip_and_reject_a_bad_block() {
+        let (tree, blocks) = forked_tree();
+        let saved = serde_json::to_value(tree.clone()).unwrap();
+        // The best chain comes first, so loading keeps the tie with c1 on b1
+        let order: Vec<&str> = saved.as_array().unwrap().iter().map(|block| block["hash"].as_str().unwrap()).collect();
+        assert_eq!(&order[..2], &[blocks[0].hash.as_str(), blocks[3].hash.as_str()]);
+
+        let loaded: BlockTree = serde_json::from_value(saved.clone()).unwrap();
+        assert_eq!(loaded.tip().block.hash, tree.tip().block.hash);
+        assert_eq!(loaded.block_count(), tree.block_count());
+        assert_eq!(hashes(&loaded.orphans()), hashes(&tree.orphans()));
+
+        let mut tampered = saved;
+        tampered[3]["data"] = "rewritten".into();
+        assert!(serde_json::from_value::<BlockTree>(tampered).is_err());
+        assert!(serde_json::from_value::<BlockTree>(serde_json::json!([])).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+use crate::config::Config;
+use crate::economics::{simulate_drop_out, DropOutConfig, DropOutEvent, MinerEconomics};
+use crate::error::Result;
+use crate::{dashboard, export, hash_rate};
+use crate::limits::{MiningLimits, MiningOutcome};
+use crate::rewards::{block_subsidy, format_coins, parse_coins, MAX_FEE};
+use crate::{Blockchain, SESSION_FILE};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Blockchain mining simulator. Runs the interactive menu when no subcommand is given.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// TOML file with `difficulty`, `target_time` and `retarget` settings
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Session file to resume and save
+    #[arg(long, global = true, default_value = SESSION_FILE)]
+    pub session: String,
+
+    /// How subcommands print their results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Tsv)]
+    pub format: OutputFormat,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Mine blocks one after another
+    Mine {
+        /// Number of blocks to mine
+        #[arg(long, default_value_t = 1)]
+        blocks: u64,
+        /// Data stored in every block
+        #[arg(long, default_value = "CLI block")]
+        data: String,
+        /// Fee offered per block, in coins
+        #[arg(long, default_value = "0", value_parser = parse_fee)]
+        fees: u64,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// Let several miners compete for each block
+    Compete {
+        /// Number of competing miners
+        #[arg(long, default_value_t = 3)]
+        miners: u32,
+        /// Number of blocks to mine
+        #[arg(long, default_value_t = 1)]
+        blocks: u64,
+        /// Data stored in every block
+        #[arg(long, default_value = "CLI competition block")]
+        data: String,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// Print chain statistics
+    Stats,
+    /// Set the difficulty as leading hex zeros
+    SetDifficulty {
+        zeros: u64,
+    },
+    /// Mine full screen with live hash rates, difficulty and block times; q quits
+    Dashboard {
+        /// Number of miners; more than one compete for every block
+        #[arg(long, default_value_t = 3)]
+        miners: u32,
+        /// Stop after this many blocks instead of running until q is pressed
+        #[arg(long)]
+        blocks: Option<u64>,
+        /// Data stored in every block
+        #[arg(long, default_value = "Dashboard block")]
+        data: String,
+    },
+    /// Print each configured miner's power cost, revenue and break-even difficulty
+    Economics,
+    /// Simulate the configured miners switching on and off as difficulty and the reward change
+    DropOut {
+        /// Number of blocks to simulate
+        #[arg(long, default_value_t = 600)]
+        blocks: u64,
+        /// Blocks between reward halvings
+        #[arg(long, default_value_t = 300)]
+        halving_interval: u64,
+        /// Seed for block times and winners; the same seed gives the same run
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+    /// Write per-block records and difficulty adjustments as CSV and JSON
+    Export {
+        /// Files are named <prefix>_blocks.csv, <prefix>_difficulty.json, ...
+        #[arg(long, default_value = "mining_history")]
+        prefix: String,
+    },
+}
+
+impl Command {
+    // Commands that only report on the session. They never save it, so a
+    // config file's chain settings aren't applied to what they show.
+    fn reads_only(&self) -> bool {
+        matches!(self, Command::Stats | Command::Economics | Command::DropOut { .. } | Command::Export { .. })
+    }
+}
+
+fn parse_fee(text: &str) -> std::result::Result<u64, String> {
+    parse_coins(text).ok_or_else(|| format!("'{}' is not an amount between 0 and {} coins", text, format_coins(MAX_FEE)))
+}
+
+// Anything Duration can't hold would panic when the deadline is set
+fn parse_time_limit(text: &str) -> std::result::Result<Duration, String> {
+    text.parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("'{}' is not a positive number of seconds", text))
+}
+
+#[derive(Debug, Args)]
+pub struct LimitArgs {
+    /// Give up on a block after this many seconds
+    #[arg(long, value_parser = parse_time_limit)]
+    time_limit: Option<Duration>,
+    /// Give up on a block after this many hash attempts
+    #[arg(long)]
+    max_attempts: Option<u64>,
+}
+
+impl LimitArgs {
+    fn limits(&self) -> MiningLimits {
+        MiningLimits {
+            cancel: None,
+            // A limit too far off to represent is no limit
+            deadline: self.time_limit.and_then(|limit| Instant::now().checked_add(limit)),
+            max_attempts: self.max_attempts,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Tab-separated values with a header line
+    Tsv,
+    /// One JSON object per line
+    Json,
+}
+
+// Writes one record per line to stdout, nothing else
+struct Output {
+    format: OutputFormat,
+    header_written: bool,
+}
+
+impl Output {
+    fn record(&mut self, fields: &[(&str, Value)]) {
+        match self.format {
+            OutputFormat::Json => {
+                let object: serde_json::Map<String, Value> =
+                    fields.iter().map(|(key, value)| (key.to_string(), value.clone())).collect();
+                println!("{}", Value::Object(object));
+            }
+            OutputFormat::Tsv => {
+                if !self.header_written {
+                    let keys: Vec<&str> = fields.iter().map(|(key, _)| *key).collect();
+                    println!("{}", keys.join("\t"));
+                    self.header_written = true;
+                }
+                let values: Vec<String> = fields
+                    .iter()
+                    .map(|(_, value)| match value {
+                        Value::String(text) => text.clone(),
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    })
+                    .collect();
+                println!("{}", values.join("\t"));
+            }
+        }
+    }
+}
+
+fn open_session(path: &str) -> Result<Blockchain> {
+    if Path::new(path).exists() {
+        Blockchain::load_file(path)
+    } else {
+        Ok(Blockchain::new(&mut ()))
+    }
+}
+
+pub fn run(cli: &Cli, command: &Command) -> Result<()> {
+    let config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    let mut blockchain = open_session(&cli.session)?;
+    if !command.reads_only() {
+        blockchain.apply_config(&config)?;
+    }
+    let mut output = Output { format: cli.format, header_written: false };
+
+    match command {
+        Command::Mine { blocks, data, fees, limits } => {
+            for _ in 0..*blocks {
+                let index = blockchain.chain.len() as u64;
+                let outcome = blockchain.add_mined_block(data.clone(), *fees, &limits.limits(), &mut ());
+                let (status, attempts, seconds) = match &outcome {
+                    MiningOutcome::Found(stats) => ("found".to_string(), stats.attempts, stats.total_time.as_secs_f64()),
+                    MiningOutcome::NotFound { reason, attempts } => (reason.to_string(), *attempts, 0.0),
+                };
+                let found = matches!(outcome, MiningOutcome::Found(_));
+                let last = blockchain.last_block();
+                output.record(&[
+                    ("index", json!(index)),
+                    ("status", json!(status)),
+                    ("hash", if found { json!(last.hash) } else { Value::Null }),
+                    ("nonce", if found { json!(last.nonce) } else { Value::Null }),
+                    ("attempts", json!(attempts)),
+                    ("seconds", json!(seconds)),
+                    ("hash_rate", json!(if seconds > 0.0 { attempts as f64 / seconds } else { 0.0 })),
+                    ("difficulty", json!(blockchain.target.difficulty())),
+                ]);
+                if !found {
+                    break;
+                }
+            }
+            blockchain.save_file(&cli.session)?;
+        }
+        Command::Compete { miners, blocks, data, limits } => {
+            for _ in 0..*blocks {
+                let index = blockchain.chain.len() as u64;
+                let outcome = blockchain.block_competition(data.clone(), 0, (*miners).max(1), &limits.limits(), &mut ());
+                let (status, winner, attempts, seconds) = match &outcome {
+                    MiningOutcome::Found(winner) => {
+                        let (stats, _) = blockchain.mining_stats.last().expect("a block was just mined");
+                        ("found".to_string(), json!(winner), stats.attempts, stats.total_time.as_secs_f64())
+                    }
+                    MiningOutcome::NotFound { reason, attempts } => (reason.to_string(), Value::Null, *attempts, 0.0),
+                };
+                let found = matches!(outcome, MiningOutcome::Found(_));
+                output.record(&[
+                    ("index", json!(index)),
+                    ("status", json!(status)),
+                    ("winner", winner),
+                    ("hash", if found { json!(blockchain.last_block().hash) } else { Value::Null }),
+                    ("attempts", json!(attempts)),
+                    ("seconds", json!(seconds)),
+                    ("difficulty", json!(blockchain.target.difficulty())),
+                ]);
+                if !found {
+                    break;
+                }
+            }
+            blockchain.save_file(&cli.session)?;
+        }
+        Command::Stats => {
+            let blocks = blockchain.chain.len();
+            let average_block_time = (blocks > 1).then(|| {
+                let span = blockchain.chain[blocks - 1].timestamp - blockchain.chain[0].timestamp;
+                span.num_milliseconds() as f64 / 1000.0 / (blocks - 1) as f64
+            });
+            // Estimated from the last 20 blocks' timestamps and difficulty
+            let network = hash_rate::estimate(&blockchain.block_times(), blocks - 1, (blocks - 1).min(20));
+            output.record(&[
+                ("blocks", json!(blocks)),
+                ("difficulty", json!(blockchain.target.difficulty())),
+                ("bits", json!(blockchain.target.to_string())),
+                ("target_time", json!(blockchain.target_time)),
+                ("retarget", json!(blockchain.retarget.name())),
+                ("pow", json!(blockchain.pow.name())),
+                ("average_block_time", json!(average_block_time)),
+                ("hash_rate", json!(blockchain.measured_hash_rate())),
+                ("network_hash_rate", json!(network.as_ref().map(|estimate| estimate.hash_rate))),
+                ("network_hash_rate_low", json!(network.as_ref().map(|estimate| estimate.low))),
+                ("network_hash_rate_high", json!(network.as_ref().map(|estimate| estimate.high))),
+                ("supply", json!(blockchain.total_supply())),
+            ]);
+        }
+        Command::SetDifficulty { zeros } => {
+            let change = blockchain.set_difficulty(*zeros)?;
+            output.record(&[
+                ("height", json!(change.height)),
+                ("from_bits", json!(change.from.to_string())),
+                ("to_bits", json!(change.to.to_string())),
+                ("difficulty", json!(change.to.difficulty())),
+            ]);
+            blockchain.save_file(&cli.session)?;
+        }
+        Command::Dashboard { miners, blocks, data } => {
+            let mined = dashboard::run(&mut blockchain, (*miners).max(1), *blocks, data)?;
+            blockchain.save_file(&cli.session)?;
+            output.record(&[
+                ("blocks_mined", json!(mined)),
+                ("height", json!(blockchain.last_block().index)),
+                ("difficulty", json!(blockchain.target.difficulty())),
+            ]);
+        }
+        Command::Economics => {
+            let difficulty = blockchain.target.difficulty();
+            let reward = block_subsidy(blockchain.chain.len() as u64, blockchain.halving_interval);
+            for profile in config.miner_profiles() {
+                let economics = MinerEconomics::new(&profile, difficulty, reward, config.coin_price());
+                output.record(&[
+                    ("miner", json!(profile.name)),
+                    ("hash_rate", json!(profile.hash_rate)),
+                    ("power_watts", json!(profile.power_watts)),
+                    ("electricity_price", json!(profile.electricity_price)),
+                    ("difficulty", json!(difficulty)),
+                    ("cost_per_hour", json!(economics.cost_per_hour)),
+                    ("revenue_per_hour", json!(economics.revenue_per_hour)),
+                    ("cost_per_block", json!(economics.cost_per_block)),
+                    ("revenue_per_block", json!(economics.revenue_per_block)),
+                    ("break_even_difficulty", json!(economics.break_even_difficulty)),
+                    ("profitable", json!(economics.is_profitable())),
+                ]);
+            }
+        }
+        Command::DropOut { blocks, halving_interval, seed } => {
+            let drop_out = DropOutConfig {
+                profiles: config.miner_profiles(),
+                coin_price: config.coin_price(),
+                blocks: *blocks,
+                target_time: blockchain.target_time,
+                retarget: blockchain.retarget,
+                halving_interval: (*halving_interval).max(1),
+                seed: *seed,
+            };
+            let report = simulate_drop_out(&drop_out);
+            let name = |miner: &usize| json!(drop_out.profiles[*miner].name);
+            for event in &report.events {
+                let (height, kind, miner, difficulty, break_even, reward) = match event {
+                    DropOutEvent::Joined { height, miner, difficulty } => {
+                        (height, "joined", name(miner), json!(difficulty), Value::Null, Value::Null)
+                    }
+                    DropOutEvent::Left { height, miner, difficulty, break_even } => {
+                        (height, "left", name(miner), json!(difficulty), json!(break_even), Value::Null)
+                    }
+                    DropOutEvent::Halving { height, reward } => {
+                        (height, "halving", Value::Null, Value::Null, Value::Null, json!(reward))
+                    }
+                };
+                output.record(&[
+                    ("height", json!(height)),
+                    ("event", json!(kind)),
+                    ("miner", miner),
+                    ("difficulty", difficulty),
+                    ("break_even", break_even),
+                    ("reward", reward),
+                ]);
+            }
+            if let Some(height) = report.stalled_at {
+                output.record(&[
+                    ("height", json!(height)),
+                    ("event", json!("stalled")),
+                    ("miner", Value::Null),
+                    ("difficulty", Value::Null),
+                    ("break_even", Value::Null),
+                    ("reward", Value::Null),
+                ]);
+            }
+        }
+        Command::Export { prefix } => {
+            for path in export::export(&blockchain, prefix)? {
+                output.record(&[("file", json!(path))]);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use crate::economics::{MinerProfile, DEFAULT_COIN_PRICE};
+use crate::error::{BlockchainError, Result};
+use crate::retarget::RetargetAlgorithm;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+// Settings read from a TOML file, e.g.
+//
+//     difficulty = 4
+//     target_time = 10
+//     retarget = { Lwma = { window = 20 } }
+//     coin_price = 0.0001
+//
+//     [[miners]]
+//     name = "Home desktop"
+//     hash_rate = 200e3
+//     power_watts = 150
+//     electricity_price = 0.25
+//
+// Anything left out keeps the session's current value.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // Leading hex zeros for the first block of a new chain; a chain that
+    // already has blocks keeps retargeting from where it is
+    pub difficulty: Option<u64>,
+    // Seconds per block. Only a chain with nothing but genesis can change it,
+    // since earlier blocks were retargeted with the old value.
+    pub target_time: Option<u64>,
+    // Unlike target_time this can change on an existing chain: the next block
+    // records the switch in its header, so blocks before it still validate
+    pub retarget: Option<RetargetAlgorithm>,
+    // Dollars per coin, for the mining economics
+    pub coin_price: Option<f64>,
+    // Miners for the mining economics; a built-in fleet if none are given
+    #[serde(default)]
+    pub miners: Vec<MinerProfile>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&content)?;
+        for miner in &config.miners {
+            if !(miner.hash_rate > 0.0 && miner.power_watts > 0.0 && miner.electricity_price >= 0.0) {
+                return Err(BlockchainError::InvalidConfig(format!(
+                    "miner '{}' needs a positive hash_rate and power_watts and a non-negative electricity_price",
+                    miner.name)));
+            }
+        }
+        if let Some(Err(message)) = config.retarget.map(|retarget| retarget.check()) {
+            return Err(BlockchainError::InvalidConfig(message));
+        }
+        if config.coin_price.is_some_and(|price| !price.is_finite() || price < 0.0) {
+            return Err(BlockchainError::InvalidConfig("coin_price must not be negative".to_string()));
+        }
+        Ok(config)
+    }
+
+    pub fn miner_profiles(&self) -> Vec<MinerProfile> {
+        if self.miners.is_empty() {
+            MinerProfile::default_fleet()
+        } else {
+            self.miners.clone()
+        }
+    }
+
+    pub fn coin_price(&self) -> f64 {
+        self.coin_price.unwrap_or(DEFAULT_COIN_PRICE)
+    }
+}
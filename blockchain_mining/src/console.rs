@@ -0,0 +1,57 @@
+use crate::limits::CancelToken;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+// Reads stdin on a background thread so the menu can keep listening for a
+// keypress while a block is being mined
+pub struct Console {
+    lines: Receiver<String>,
+    // Lines typed during a mining run that weren't an abort
+    pending: VecDeque<String>,
+}
+
+impl Console {
+    pub fn spawn() -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            let mut line = String::new();
+            while io::stdin().read_line(&mut line).is_ok_and(|n| n > 0) {
+                if sender.send(line.clone()).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        });
+        Console { lines, pending: VecDeque::new() }
+    }
+
+    // Next line of input, or an empty string once stdin is closed
+    pub fn read_line(&mut self) -> String {
+        self.pending
+            .pop_front()
+            .or_else(|| self.lines.recv().ok())
+            .unwrap_or_default()
+    }
+
+    // Runs `work` on another thread and cancels `token` if Enter is pressed
+    // before it finishes. Anything else typed meanwhile is kept for later.
+    pub fn abort_on_enter<T: Send>(&mut self, token: &CancelToken, work: impl FnOnce() -> T + Send) -> T {
+        println!("⏎  Press Enter to abort mining");
+        thread::scope(|scope| {
+            let handle = scope.spawn(work);
+            while !handle.is_finished() {
+                match self.lines.recv_timeout(Duration::from_millis(100)) {
+                    Ok(line) if line.trim().is_empty() => token.cancel(),
+                    Ok(line) => self.pending.push_back(line),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // No more input can arrive; just wait for the result
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            handle.join().expect("mining thread panicked")
+        })
+    }
+}
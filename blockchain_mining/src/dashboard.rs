@@ -0,0 +1,362 @@
+use crate::events::{MiningEvent, MiningObserver};
+use crate::limits::{MiningLimits, MiningOutcome};
+use crate::rewards::format_coins;
+use crate::target::Target;
+use crate::Blockchain;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::Line;
+use ratatui::widgets::{Axis, Bar, BarChart, Block, Chart, Dataset, GraphType, Paragraph, Sparkline};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::time::{Duration, Instant};
+
+// Redraw at most this often; mining emits events far faster
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+// Per-miner hash rates are measured over windows at least this long
+const RATE_WINDOW: Duration = Duration::from_millis(500);
+// Blocks shown in the difficulty sparkline and block-time chart
+const HISTORY: usize = 60;
+
+// Everything on screen, so drawing doesn't need the chain borrowed
+#[derive(Default)]
+struct DashboardState {
+    // Copied from the chain before each block
+    pow: String,
+    retarget: String,
+    target_time: u64,
+    tip_index: u64,
+    tip_hash: String,
+    tip_miner: String,
+    tip_mined_at: String,
+    supply: Option<u64>,
+    difficulties: Vec<u64>,
+    // (height, seconds since the previous block)
+    block_times: Vec<(f64, f64)>,
+
+    // The block being mined
+    index: u64,
+    target: Option<Target>,
+    attempts: Vec<u64>,
+    elapsed: Duration,
+    rates: Vec<f64>,
+    rate_sample: (Vec<u64>, Duration),
+    winner: Option<u32>,
+
+    blocks_mined: u64,
+    // The last thing worth telling the user
+    status: String,
+    // Mining is over and the screen waits for a key
+    finished: bool,
+}
+
+impl DashboardState {
+    fn sync(&mut self, blockchain: &Blockchain) {
+        let tip = blockchain.last_block();
+        self.pow = blockchain.pow.name().to_string();
+        self.retarget = blockchain.retarget.name();
+        self.target_time = blockchain.target_time;
+        self.tip_index = tip.index;
+        self.tip_hash = tip.hash.clone();
+        self.tip_miner = tip.coinbase.miner.clone();
+        self.tip_mined_at = tip.timestamp.format("%H:%M:%S").to_string();
+        self.supply = blockchain.total_supply();
+
+        let recent = &blockchain.chain[blockchain.chain.len().saturating_sub(HISTORY + 1)..];
+        self.difficulties = recent.iter().map(|block| block.target.difficulty() as u64).collect();
+        self.block_times = recent
+            .windows(2)
+            .map(|pair| {
+                let seconds = (pair[1].timestamp - pair[0].timestamp).num_milliseconds() as f64 / 1000.0;
+                (pair[1].index as f64, seconds)
+            })
+            .collect();
+    }
+
+    fn update(&mut self, event: &MiningEvent) {
+        match event {
+            MiningEvent::Started { index, target, miners } => {
+                let miners = miners.unwrap_or(1) as usize;
+                self.index = *index;
+                self.target = Some(*target);
+                self.attempts = vec![0; miners];
+                self.elapsed = Duration::ZERO;
+                self.rates = vec![0.0; miners];
+                self.rate_sample = (vec![0; miners], Duration::ZERO);
+                self.winner = None;
+            }
+            MiningEvent::Hashing { attempts_per_miner, elapsed } => {
+                self.attempts = attempts_per_miner.clone();
+                self.elapsed = *elapsed;
+                let window = *elapsed - self.rate_sample.1;
+                if window >= RATE_WINDOW {
+                    self.rates = attempts_per_miner
+                        .iter()
+                        .zip(&self.rate_sample.0)
+                        .map(|(now, before)| (now - before) as f64 / window.as_secs_f64())
+                        .collect();
+                    self.rate_sample = (attempts_per_miner.clone(), *elapsed);
+                }
+            }
+            MiningEvent::MinerWon { miner, .. } => {
+                self.winner = Some(*miner);
+            }
+            MiningEvent::Found { index, nonce, attempts, elapsed, .. } => {
+                self.blocks_mined += 1;
+                let by = self.winner.map(|miner| format!(" by miner {}", miner)).unwrap_or_default();
+                self.status = format!("🎉 Block {} found{}: nonce {} after {} attempts in {:.2}s",
+                                      index, by, nonce, attempts, elapsed.as_secs_f64());
+            }
+            MiningEvent::Stopped { reason, attempts, .. } => {
+                self.status = format!("🛑 Mining stopped ({}) after {} attempts", reason, attempts);
+            }
+            MiningEvent::Retargeted { from, to, .. } if from != to => {
+                self.status = format!("🎯 Retargeted: difficulty {:.0} → {:.0}", from.difficulty(), to.difficulty());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn format_rate(hashes_per_second: f64) -> String {
+    if hashes_per_second >= 1e6 {
+        format!("{:.1} MH/s", hashes_per_second / 1e6)
+    } else if hashes_per_second >= 1e3 {
+        format!("{:.1} kH/s", hashes_per_second / 1e3)
+    } else {
+        format!("{:.0} H/s", hashes_per_second)
+    }
+}
+
+fn render(frame: &mut Frame, state: &DashboardState) {
+    let [header, blocks, live, times, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(7),
+        Constraint::Min(8),
+        Constraint::Min(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(
+        Line::from(format!("⛏️  Mining dashboard │ {} │ {} │ {} blocks mined this session",
+                           state.pow, state.retarget, state.blocks_mined)).bold(),
+        header,
+    );
+
+    let [current, tip] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(blocks);
+    render_current_block(frame, current, state);
+    render_tip(frame, tip, state);
+
+    let [rates, difficulty] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(live);
+    render_rates(frame, rates, state);
+    render_difficulty(frame, difficulty, state);
+
+    render_block_times(frame, times, state);
+
+    frame.render_widget(
+        Line::from(if state.finished { state.status.clone() } else { format!("{}   (q to quit)", state.status) })
+            .fg(Color::Gray),
+        footer,
+    );
+}
+
+fn render_current_block(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let total: u64 = state.attempts.iter().sum();
+    let seconds = state.elapsed.as_secs_f64();
+    let mut lines = vec![Line::from(format!("Height      {}", state.index))];
+    if let Some(target) = state.target {
+        lines.push(Line::from(format!("Difficulty  {:.0} (bits {})", target.difficulty(), target)));
+        // Chance the block would have been found by now, to show how lucky a slow block is
+        let progress = 1.0 - (-(total as f64) / target.difficulty()).exp();
+        lines.push(Line::from(format!("Attempts    {} ({:.0}% of blocks are found by now)", total, progress * 100.0)));
+    }
+    lines.push(Line::from(format!("Elapsed     {:.1}s", seconds)));
+    let average = if seconds > 0.0 { total as f64 / seconds } else { 0.0 };
+    lines.push(Line::from(format!("Hash rate   {}", format_rate(average))));
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Mining ")), area);
+}
+
+fn render_tip(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let lines = vec![
+        Line::from(format!("Height      {}", state.tip_index)),
+        Line::from(format!("Hash        {}", state.tip_hash)),
+        Line::from(format!("Miner       {}", if state.tip_miner.is_empty() { "-" } else { &state.tip_miner })),
+        Line::from(format!("Mined at    {}", state.tip_mined_at)),
+        Line::from(format!("Supply      {}", state.supply.map_or("overflow".to_string(), format_coins))),
+    ];
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Chain tip ")), area);
+}
+
+fn render_rates(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let bars: Vec<Bar> = state.rates
+        .iter()
+        .enumerate()
+        .map(|(miner, rate)| {
+            Bar::with_label(format!("Miner {}", miner + 1), *rate as u64).text_value(format_rate(*rate))
+        })
+        .collect();
+    // Wide enough for the value text, narrow enough that a few miners fit
+    let width = (area.width.saturating_sub(2) / bars.len().max(1) as u16).saturating_sub(1).clamp(3, 12);
+    let chart = BarChart::vertical(bars)
+        .bar_width(width)
+        .bar_gap(1)
+        .bar_style(Style::new().fg(Color::Yellow))
+        .block(Block::bordered().title(" Hash rate per miner "));
+    frame.render_widget(chart, area);
+}
+
+fn render_difficulty(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    // The chain's recent blocks followed by the block being mined
+    let mut difficulties = state.difficulties.clone();
+    difficulties.extend(state.target.map(|target| target.difficulty() as u64));
+    let title = format!(" Difficulty, last {} blocks (now {}) ",
+                        difficulties.len(), difficulties.last().copied().unwrap_or_default());
+    let sparkline = Sparkline::default()
+        .data(&difficulties)
+        .style(Style::new().fg(Color::Cyan))
+        .block(Block::bordered().title(title));
+    frame.render_widget(sparkline, area);
+}
+
+fn render_block_times(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let target_time = state.target_time as f64;
+    let (first, last) = match (state.block_times.first(), state.block_times.last()) {
+        (Some(first), Some(last)) => (first.0, last.0.max(first.0 + 1.0)),
+        _ => (0.0, 1.0),
+    };
+    let slowest = state.block_times.iter().map(|(_, seconds)| *seconds).fold(0.0, f64::max);
+    let top = slowest.max(target_time * 2.0).max(1.0);
+    let target_line = [(first, target_time), (last, target_time)];
+
+    let datasets = vec![
+        Dataset::default()
+            .name("block time")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::new().fg(Color::Green))
+            .data(&state.block_times),
+        Dataset::default()
+            .name(format!("target {}s", state.target_time))
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::new().fg(Color::Red))
+            .data(&target_line),
+    ];
+    let chart = Chart::new(datasets)
+        .block(Block::bordered().title(" Recent block times vs target "))
+        .x_axis(Axis::default()
+            .title("height")
+            .bounds([first, last])
+            .labels([format!("{:.0}", first), format!("{:.0}", last)]))
+        .y_axis(Axis::default()
+            .title("s")
+            .bounds([0.0, top])
+            .labels(["0".to_string(), format!("{:.0}", top / 2.0), format!("{:.0}", top)]));
+    frame.render_widget(chart, area);
+}
+
+// Watches mining through the observer hooks, redrawing as events come in and
+// cancelling when the user presses q
+struct Dashboard {
+    terminal: DefaultTerminal,
+    state: DashboardState,
+    last_draw: Option<Instant>,
+    quit: bool,
+    // The first error drawing or reading keys; stops the session
+    error: Option<io::Error>,
+}
+
+impl Dashboard {
+    fn draw(&mut self, force: bool) {
+        if !force && self.last_draw.is_some_and(|last| last.elapsed() < FRAME_INTERVAL) {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+        if let Err(e) = self.poll_keys().and_then(|()| self.terminal.draw(|frame| render(frame, &self.state)).map(|_| ())) {
+            self.error.get_or_insert(e);
+            self.quit = true;
+        }
+    }
+
+    fn poll_keys(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press
+                    && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || ctrl_c)
+                {
+                    self.quit = true;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MiningObserver for Dashboard {
+    fn on_event(&mut self, event: &MiningEvent) {
+        self.state.update(event);
+        self.draw(false);
+    }
+
+    fn should_cancel(&self) -> bool {
+        self.quit
+    }
+}
+
+// Mines blocks full screen until the user quits or `blocks` have been mined.
+// One miner mines alone; more compete for every block. Returns how many blocks
+// were mined.
+pub fn run(blockchain: &mut Blockchain, miners: u32, blocks: Option<u64>, data: &str) -> io::Result<u64> {
+    let mut dashboard = Dashboard {
+        terminal: ratatui::try_init()?,
+        state: DashboardState::default(),
+        last_draw: None,
+        quit: false,
+        error: None,
+    };
+
+    let limits = MiningLimits::none();
+    while !dashboard.quit && blocks.is_none_or(|blocks| dashboard.state.blocks_mined < blocks) {
+        dashboard.state.sync(blockchain);
+        dashboard.draw(true);
+        let found = if miners > 1 {
+            matches!(blockchain.block_competition(data.to_string(), 0, miners, &limits, &mut dashboard),
+                     MiningOutcome::Found(_))
+        } else {
+            matches!(blockchain.add_mined_block(data.to_string(), 0, &limits, &mut dashboard),
+                     MiningOutcome::Found(_))
+        };
+        if !found {
+            break;
+        }
+    }
+
+    // Leave the final state up until a key is pressed, unless the user
+    // already quit
+    if !dashboard.quit && dashboard.error.is_none() {
+        dashboard.state.sync(blockchain);
+        dashboard.state.status = format!("✅ Mined {} blocks, press any key to exit", dashboard.state.blocks_mined);
+        dashboard.state.finished = true;
+        dashboard.draw(true);
+        loop {
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break,
+                Ok(_) => {}
+                Err(e) => {
+                    dashboard.error = Some(e);
+                    break;
+                }
+            }
+        }
+    }
+
+    ratatui::restore();
+    match dashboard.error {
+        Some(e) => Err(e),
+        None => Ok(dashboard.state.blocks_mined),
+    }
+}
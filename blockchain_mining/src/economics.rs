@@ -0,0 +1,345 @@
+use crate::retarget::{BlockTime, RetargetAlgorithm};
+use crate::rewards::{block_subsidy, COIN};
+use crate::target::Target;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+// Dollars per coin when the config doesn't say. Tiny, because this
+// simulator's hash rates and difficulties are tiny compared to real networks.
+pub const DEFAULT_COIN_PRICE: f64 = 0.0001;
+// A miner that switched off comes back once the difficulty is this far below
+// its break-even point, so it doesn't flap on every retarget
+const REJOIN_MARGIN: f64 = 0.9;
+// Miners judge profitability on the average difficulty of this many recent
+// blocks rather than reacting to every retarget swing
+const DECISION_WINDOW: usize = 20;
+const JOULES_PER_KWH: f64 = 3_600_000.0;
+
+// One miner's hardware and power bill, e.g. in the config file
+//
+//     [[miners]]
+//     name = "Hydro farm"
+//     hash_rate = 5e6
+//     power_watts = 1500
+//     electricity_price = 0.04
+//     joins_at = 200
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MinerProfile {
+    pub name: String,
+    // Hashes per second
+    pub hash_rate: f64,
+    pub power_watts: f64,
+    // Dollars per kWh
+    pub electricity_price: f64,
+    // Height at which the miner switches on, for the drop-out simulation
+    #[serde(default)]
+    pub joins_at: u64,
+}
+
+impl MinerProfile {
+    // A fleet that tells the story with the default target time and retarget:
+    // the hydro farm joining pushes the laptop out, the halving then pushes
+    // out the desktop
+    pub fn default_fleet() -> Vec<MinerProfile> {
+        let miner = |name: &str, hash_rate, power_watts, electricity_price, joins_at| MinerProfile {
+            name: name.to_string(),
+            hash_rate,
+            power_watts,
+            electricity_price,
+            joins_at,
+        };
+        vec![
+            miner("Old laptop", 50e3, 60.0, 0.30, 0),
+            miner("Home desktop", 200e3, 150.0, 0.25, 0),
+            miner("City data centre", 1e6, 600.0, 0.15, 0),
+            miner("Hydro farm", 5e6, 1500.0, 0.04, 200),
+        ]
+    }
+
+    pub fn cost_per_hour(&self) -> f64 {
+        self.power_watts / 1000.0 * self.electricity_price
+    }
+
+    // Hashes per joule; what decides who survives
+    pub fn efficiency(&self) -> f64 {
+        self.hash_rate / self.power_watts
+    }
+}
+
+// What mining is worth to one miner at a given difficulty and block reward
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinerEconomics {
+    pub cost_per_hour: f64,
+    pub revenue_per_hour: f64,
+    // Electricity spent, on average, for each block this miner finds
+    pub cost_per_block: f64,
+    pub revenue_per_block: f64,
+    // The difficulty at which cost per block equals the reward
+    pub break_even_difficulty: f64,
+}
+
+impl MinerEconomics {
+    // A block takes `difficulty` hashes on average, whoever else is mining,
+    // so the miner finds one every difficulty / hash_rate seconds
+    pub fn new(profile: &MinerProfile, difficulty: f64, reward: u64, coin_price: f64) -> Self {
+        let revenue_per_block = reward as f64 / COIN as f64 * coin_price;
+        let joules_per_hash = profile.power_watts / profile.hash_rate;
+        let cost_per_hash = joules_per_hash / JOULES_PER_KWH * profile.electricity_price;
+        let blocks_per_hour = profile.hash_rate * 3600.0 / difficulty;
+        MinerEconomics {
+            cost_per_hour: profile.cost_per_hour(),
+            revenue_per_hour: blocks_per_hour * revenue_per_block,
+            cost_per_block: cost_per_hash * difficulty,
+            revenue_per_block,
+            break_even_difficulty: revenue_per_block / cost_per_hash,
+        }
+    }
+
+    pub fn is_profitable(&self) -> bool {
+        self.revenue_per_hour > self.cost_per_hour
+    }
+}
+
+pub fn display_economics(profiles: &[MinerProfile], difficulty: f64, reward: u64, coin_price: f64) {
+    println!("\n💸 MINING ECONOMICS at difficulty {:.0}, reward {:.8} coins at ${} per coin",
+             difficulty, reward as f64 / COIN as f64, coin_price);
+    println!("───────────────────────────");
+    for profile in profiles {
+        let economics = MinerEconomics::new(profile, difficulty, reward, coin_price);
+        println!("{} {} ({:.0} H/s, {:.0} W, ${:.2}/kWh, {:.0} H/J)",
+                 if economics.is_profitable() { "✅" } else { "❌" },
+                 profile.name, profile.hash_rate, profile.power_watts, profile.electricity_price,
+                 profile.efficiency());
+        println!("   ⚡ Power bill ${:.4}/h, expected revenue ${:.4}/h, profit ${:.4}/h",
+                 economics.cost_per_hour, economics.revenue_per_hour,
+                 economics.revenue_per_hour - economics.cost_per_hour);
+        println!("   🧱 Cost per block found ${:.6} vs reward ${:.6}; break-even difficulty {:.0}",
+                 economics.cost_per_block, economics.revenue_per_block, economics.break_even_difficulty);
+    }
+    println!("───────────────────────────\n");
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MinerResult {
+    pub blocks_won: u64,
+    pub hours_mining: f64,
+    pub revenue: f64,
+    pub cost: f64,
+}
+
+pub struct DropOutConfig {
+    pub profiles: Vec<MinerProfile>,
+    pub coin_price: f64,
+    pub blocks: u64,
+    pub target_time: u64,
+    pub retarget: RetargetAlgorithm,
+    pub halving_interval: u64,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropOutEvent {
+    // `difficulty` is the recent average the miner decided on
+    Joined { height: u64, miner: usize, difficulty: f64 },
+    Left { height: u64, miner: usize, difficulty: f64, break_even: f64 },
+    Halving { height: u64, reward: u64 },
+}
+
+pub struct DropOutReport {
+    pub events: Vec<DropOutEvent>,
+    pub results: Vec<MinerResult>,
+    // (height, average difficulty, active hash rate), about 20 spread over the run
+    pub samples: Vec<(u64, f64, f64)>,
+    // Set when every miner switched off and the chain stalled
+    pub stalled_at: Option<u64>,
+}
+
+// Miners switch on when their `joins_at` height arrives and the difficulty
+// pays for their electricity, and switch off when it no longer does. Block
+// times are drawn from the exponential distribution for the active hash rate,
+// so the retarget sees what a real network would.
+//
+// This is the competition mode with a power bill. The miners in
+// `block_competition` are threads on one machine hashing at the same speed,
+// so profiles with their own hash rate and electricity price only make sense
+// here, where the competition is modelled rather than run.
+pub fn simulate_drop_out(config: &DropOutConfig) -> DropOutReport {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let strategy = config.retarget.strategy();
+    let miners = config.profiles.len();
+    let mut active = vec![false; miners];
+    let mut results = vec![MinerResult::default(); miners];
+    let mut events = Vec::new();
+    let mut samples = Vec::new();
+
+    // Start at the difficulty that suits the miners present from the start
+    let initial_rate: f64 = config.profiles.iter().filter(|p| p.joins_at == 0).map(|p| p.hash_rate).sum();
+    let mut target = Target::from_difficulty((initial_rate * config.target_time as f64).max(1.0));
+    let mut history = vec![BlockTime { timestamp: 0, target }];
+    let mut now = 0.0;
+    let sample_every = (config.blocks / 20).max(1);
+
+    for height in 1..=config.blocks {
+        target = strategy.next_target(&history, target, config.target_time);
+        let difficulty = target.difficulty();
+        let reward = block_subsidy(height, config.halving_interval);
+        if height > 1 && reward != block_subsidy(height - 1, config.halving_interval) {
+            events.push(DropOutEvent::Halving { height, reward });
+        }
+
+        let recent = &history[history.len().saturating_sub(DECISION_WINDOW - 1)..];
+        let judged = (recent.iter().map(|block| block.target.difficulty()).sum::<f64>() + difficulty)
+            / (recent.len() + 1) as f64;
+        for (miner, profile) in config.profiles.iter().enumerate() {
+            if height < profile.joins_at {
+                continue;
+            }
+            let break_even = MinerEconomics::new(profile, judged, reward, config.coin_price).break_even_difficulty;
+            if active[miner] && judged > break_even {
+                active[miner] = false;
+                events.push(DropOutEvent::Left { height, miner, difficulty: judged, break_even });
+            } else if !active[miner] && judged < break_even * REJOIN_MARGIN {
+                active[miner] = true;
+                events.push(DropOutEvent::Joined { height, miner, difficulty: judged });
+            }
+        }
+
+        let rates: Vec<f64> = config.profiles
+            .iter()
+            .zip(&active)
+            .map(|(profile, on)| if *on { profile.hash_rate } else { 0.0 })
+            .collect();
+        let network_rate: f64 = rates.iter().sum();
+        if network_rate == 0.0 {
+            return DropOutReport { events, results, samples, stalled_at: Some(height) };
+        }
+
+        // Exponential solve time, then a winner in proportion to hash rate
+        let solve_time = -(1.0 - rng.gen::<f64>()).ln() * difficulty / network_rate;
+        now += solve_time;
+        let mut pick = rng.gen::<f64>() * network_rate;
+        let winner = rates.iter().position(|rate| {
+            pick -= rate;
+            pick < 0.0
+        }).unwrap_or(miners - 1);
+
+        for (miner, profile) in config.profiles.iter().enumerate() {
+            if active[miner] {
+                let result = &mut results[miner];
+                result.hours_mining += solve_time / 3600.0;
+                result.cost += solve_time / 3600.0 * profile.cost_per_hour();
+            }
+        }
+        results[winner].blocks_won += 1;
+        results[winner].revenue += reward as f64 / COIN as f64 * config.coin_price;
+
+        history.push(BlockTime { timestamp: now as i64, target });
+        if height % sample_every == 0 {
+            samples.push((height, judged, network_rate));
+        }
+    }
+
+    DropOutReport { events, results, samples, stalled_at: None }
+}
+
+pub fn display_drop_out(config: &DropOutConfig) {
+    println!("\n🏭 MINERS vs DIFFICULTY: {} blocks, {}, halving every {} blocks, ${} per coin",
+             config.blocks, config.retarget.name(), config.halving_interval, config.coin_price);
+    println!("───────────────────────────");
+    let report = simulate_drop_out(config);
+    let name = |miner: usize| &config.profiles[miner].name;
+
+    for event in &report.events {
+        match event {
+            DropOutEvent::Joined { height, miner, difficulty } => {
+                println!("🟢 Block {:>5}: {} starts mining, average difficulty {:.0}", height, name(*miner), difficulty);
+            }
+            DropOutEvent::Left { height, miner, difficulty, break_even } => {
+                println!("🔴 Block {:>5}: {} switches off, average difficulty {:.0} is above its break-even {:.0}",
+                         height, name(*miner), difficulty, break_even);
+            }
+            DropOutEvent::Halving { height, reward } => {
+                println!("✂️  Block {:>5}: reward halves to {:.8} coins", height, *reward as f64 / COIN as f64);
+            }
+        }
+    }
+    if let Some(height) = report.stalled_at {
+        println!("🧊 Block {:>5}: nobody can mine profitably any more, the chain stalls", height);
+    }
+
+    println!("\n📈 Average difficulty over the last {} blocks and active hash rate:", DECISION_WINDOW);
+    for (height, difficulty, rate) in &report.samples {
+        println!("   Block {:>5}: difficulty {:>12.0}, hash rate {:>10.0} H/s", height, difficulty, rate);
+    }
+
+    println!("\n💰 Results per miner:");
+    for (miner, result) in report.results.iter().enumerate() {
+        println!("   {:<18} {:>5} blocks, {:>7.2}h mining, revenue ${:.4}, power ${:.4}, profit ${:.4}",
+                 name(miner), result.blocks_won, result.hours_mining, result.revenue, result.cost,
+                 result.revenue - result.cost);
+    }
+    println!("───────────────────────────");
+    println!("Every miner's cost per block grows with difficulty, but the reward");
+    println!("doesn't. When efficient hardware joins, difficulty rises past what");
+    println!("the least efficient miners can pay for, and they switch off.\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> MinerProfile {
+        MinerProfile {
+            name: "Test".to_string(),
+            hash_rate: 1000.0,
+            power_watts: 100.0,
+            electricity_price: 0.36,
+            joins_at: 0,
+        }
+    }
+
+    #[test]
+    fn breaks_even_where_cost_per_block_meets_reward() {
+        let first = MinerEconomics::new(&profile(), 1000.0, 50 * COIN, 1.0);
+        let at_break_even = MinerEconomics::new(&profile(), first.break_even_difficulty, 50 * COIN, 1.0);
+        assert!((at_break_even.cost_per_block - at_break_even.revenue_per_block).abs() < 1e-9);
+        assert!((at_break_even.cost_per_hour - at_break_even.revenue_per_hour).abs() < 1e-9);
+        // 100 W at $0.36/kWh is $0.01 per 1000 seconds; 1000 H/s means 1e-8 per hash
+        assert!((first.break_even_difficulty - 50.0 / 1e-8).abs() < 1.0);
+    }
+
+    fn fleet_run(seed: u64) -> DropOutConfig {
+        DropOutConfig {
+            profiles: MinerProfile::default_fleet(),
+            coin_price: DEFAULT_COIN_PRICE,
+            blocks: 600,
+            target_time: 10,
+            retarget: RetargetAlgorithm::default(),
+            halving_interval: 300,
+            seed,
+        }
+    }
+
+    #[test]
+    fn least_efficient_miner_leaves_first() {
+        let config = fleet_run(42);
+        let report = simulate_drop_out(&config);
+        let first_to_leave = report.events.iter().find_map(|event| match event {
+            DropOutEvent::Left { miner, .. } => Some(*miner),
+            _ => None,
+        });
+        assert_eq!(first_to_leave, Some(0));
+    }
+
+    #[test]
+    fn the_same_seed_replays_the_same_run() {
+        let first = simulate_drop_out(&fleet_run(7));
+        let second = simulate_drop_out(&fleet_run(7));
+        assert_eq!(first.events, second.events);
+        assert_eq!(first.samples, second.samples);
+        let blocks: u64 = first.results.iter().map(|result| result.blocks_won).sum();
+        assert_eq!(blocks, first.stalled_at.map_or(600, |height| height - 1));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::pow::PowAlgorithm;
+use crate::target::Target;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum BlockchainError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Config error: {0}")]
+    TomlError(#[from] toml::de::Error),
+
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+
+    #[error("Invalid difficulty: {0}")]
+    InvalidDifficulty(String),
+
+    #[error("Invalid block: {index}: {message}")]
+    InvalidBlock { index: u64, message: String },
+
+    #[error("Chain is empty")]
+    EmptyChain,
+
+    #[error("Invalid genesis block: {0}")]
+    InvalidGenesis(String),
+
+    #[error("Invalid index: expected {expected}, found {found}")]
+    InvalidIndex { expected: u64, found: u64 },
+
+    #[error("Invalid previous hash in block {index}: expected {expected}, found {found}")]
+    InvalidPreviousHash { index: u64, expected: String, found: String },
+
+    #[error("Invalid hash in block {index}: expected {expected}, found {found}")]
+    InvalidHash { index: u64, expected: String, found: String },
+
+    #[error("Insufficient proof of work in block {index}: hash {hash} is above target {target}")]
+    InsufficientWork { index: u64, hash: String, target: Target },
+
+    #[error("Unexpected difficulty in block {index}: expected bits {expected}, found {found}")]
+    UnexpectedTarget { index: u64, expected: Target, found: Target },
+
+    #[error("Invalid coinbase subsidy in block {index}: expected {expected}, found {found}")]
+    InvalidReward { index: u64, expected: u64, found: u64 },
+
+    #[error("Excessive fees in block {index}: at most {max}, found {found}")]
+    ExcessiveFees { index: u64, max: u64, found: u64 },
+
+    #[error("Invalid timestamp in block {index}: {message}")]
+    InvalidTimestamp { index: u64, message: String },
+
+    #[error("Invalid proof of work parameters for {algorithm}: {message}")]
+    InvalidPow { algorithm: PowAlgorithm, message: String },
+
+    #[error("Wrong proof of work in block {index}: the chain uses {expected}, found {found}")]
+    UnexpectedPow { index: u64, expected: PowAlgorithm, found: PowAlgorithm },
+}
+
+pub type Result<T> = std::result::Result<T, BlockchainError>;
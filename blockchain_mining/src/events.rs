@@ -0,0 +1,182 @@
+use crate::limits::StopReason;
+use crate::target::{to_hex, Target};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+// Everything worth reporting while a block is being mined
+#[derive(Debug, Clone, PartialEq)]
+pub enum MiningEvent {
+    // `miners` is set when several miners compete for the block
+    Started { index: u64, target: Target, miners: Option<u32> },
+    Progress { attempts: u64, hps: f64, nonce: u64, hash: String },
+    // Frequent running totals for live displays, one count per miner
+    Hashing { attempts_per_miner: Vec<u64>, elapsed: Duration },
+    // Sent just before `Found` when a competition has a winner
+    MinerWon { miner: u32, attempts_per_miner: Vec<u64> },
+    Found { index: u64, nonce: u64, hash: String, attempts: u64, elapsed: Duration },
+    // Gave up without finding the block
+    Stopped { reason: StopReason, attempts: u64, elapsed: Duration },
+    // The target was recomputed before mining the next block
+    Retargeted { average_block_time: Option<f64>, from: Target, to: Target },
+}
+
+pub trait MiningObserver {
+    fn on_event(&mut self, event: &MiningEvent);
+
+    // Polled every few thousand hashes; return true to stop mining
+    fn should_cancel(&self) -> bool {
+        false
+    }
+}
+
+// Mine quietly
+impl MiningObserver for () {
+    fn on_event(&mut self, _event: &MiningEvent) {}
+}
+
+// Forward events to another thread
+impl MiningObserver for Sender<MiningEvent> {
+    fn on_event(&mut self, event: &MiningEvent) {
+        // Nobody listening any more is not our problem
+        let _ = self.send(event.clone());
+    }
+}
+
+// The interactive CLI's progress output
+pub struct ConsoleObserver;
+
+impl MiningObserver for ConsoleObserver {
+    fn on_event(&mut self, event: &MiningEvent) {
+        match event {
+            MiningEvent::Started { index, target, miners: None } => {
+                println!("\n🚀 STARTING TO MINE BLOCK {}", index);
+                println!("📊 Current difficulty: {:.0} (bits {})", target.difficulty(), target);
+                println!("🎯 Target: hash <= {}", to_hex(&target.to_bytes()));
+                println!("⚡ Mining block {}...", index);
+            }
+            MiningEvent::Started { index, target, miners: Some(miners) } => {
+                println!("🏁 MINING COMPETITION!");
+                println!("🏭 {} miners competing for block {}", miners, index);
+                println!("🎯 Target: {}", to_hex(&target.to_bytes()));
+            }
+            MiningEvent::Progress { attempts, hps, nonce, hash } => {
+                println!("   💭 Attempt {}: nonce={}, hash={}... ({:.0} H/s)",
+                         attempts, nonce, &hash[..8], hps);
+            }
+            MiningEvent::Hashing { .. } => {}
+            MiningEvent::MinerWon { miner, attempts_per_miner } => {
+                let total_attempts: u64 = attempts_per_miner.iter().sum();
+                println!("🏆 WINNER: MINER {}!", miner);
+                println!("   📊 Total attempts by all miners: {}", total_attempts);
+                for (i, attempts) in attempts_per_miner.iter().enumerate() {
+                    let percentage = (*attempts as f64 / total_attempts as f64) * 100.0;
+                    println!("      Miner {}: {} attempts ({:.1}%)", i + 1, attempts, percentage);
+                }
+            }
+            MiningEvent::Found { nonce, hash, attempts, elapsed, .. } => {
+                let hps = *attempts as f64 / elapsed.as_secs_f64();
+                println!("🎉 BLOCK MINED!");
+                println!("   🔢 Winning nonce: {}", nonce);
+                println!("   🔐 Final hash: {}", hash);
+                println!("   ⏱️  Time: {:.2}s", elapsed.as_secs_f64());
+                println!("   ⚡ Speed: {:.0} hashes/second", hps);
+            }
+            MiningEvent::Stopped { reason, attempts, elapsed } => {
+                println!("🛑 Mining stopped ({}) after {} attempts ({:.2}s), no block found",
+                         reason, attempts, elapsed.as_secs_f64());
+            }
+            MiningEvent::Retargeted { average_block_time, from, to } => {
+                if let Some(average) = average_block_time {
+                    println!("⏱️ Average block mining time: {:.2}s", average);
+                }
+                let factor = from.as_f64() / to.as_f64();
+                if factor > 1.0 {
+                    println!("⬆️ Increasing difficulty to {:.0} (x{:.2})", to.difficulty(), factor);
+                } else if factor < 1.0 {
+                    println!("⬇️ Decreasing difficulty to {:.0} (x{:.2})", to.difficulty(), factor);
+                } else {
+                    println!("↔️ Difficulty stays at {:.0}", to.difficulty());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{MiningLimits, MiningOutcome};
+    use crate::Blockchain;
+
+    #[derive(Default)]
+    struct Recorder(Vec<MiningEvent>);
+
+    impl MiningObserver for Recorder {
+        fn on_event(&mut self, event: &MiningEvent) {
+            self.0.push(event.clone());
+        }
+    }
+
+    // Retargeted, Started, any amount of Progress and Hashing, then `last`
+    fn assert_run(events: &[MiningEvent], last: fn(&MiningEvent) -> bool) {
+        assert!(matches!(events[0], MiningEvent::Retargeted { .. }), "{:?}", events[0]);
+        assert!(matches!(events[1], MiningEvent::Started { .. }), "{:?}", events[1]);
+        for event in &events[2..events.len() - 1] {
+            assert!(matches!(event, MiningEvent::Progress { .. } | MiningEvent::Hashing { .. }), "{:?}", event);
+        }
+        assert!(last(&events[events.len() - 1]), "{:?}", events[events.len() - 1]);
+    }
+
+    #[test]
+    fn a_run_that_gives_up_reports_progress_then_stops() {
+        let mut blockchain = Blockchain::new(&mut ());
+        blockchain.set_difficulty(16).unwrap();
+        let limits = MiningLimits { max_attempts: Some(50_001), ..MiningLimits::none() };
+        let mut recorder = Recorder::default();
+        let outcome = blockchain.add_mined_block("unlucky".to_string(), 0, &limits, &mut recorder);
+        assert!(matches!(outcome, MiningOutcome::NotFound { .. }));
+
+        let events = &recorder.0;
+        assert_run(events, |event| matches!(event, MiningEvent::Stopped { attempts: 50_001, .. }));
+        assert!(events.iter().any(|event| matches!(event, MiningEvent::Progress { attempts: 50_000, .. })));
+        assert!(events.iter().any(|event| matches!(event, MiningEvent::Hashing { .. })));
+    }
+
+    #[test]
+    fn found_blocks_and_retargets_report_what_happened() {
+        let mut blockchain = Blockchain::new(&mut ());
+        for _ in 0..3 {
+            let from = blockchain.target;
+            let mut recorder = Recorder::default();
+            let outcome = blockchain.add_mined_block("found".to_string(), 0, &MiningLimits::none(), &mut recorder);
+            assert!(matches!(outcome, MiningOutcome::Found(_)));
+
+            let tip = blockchain.last_block();
+            assert_run(&recorder.0, |event| matches!(event, MiningEvent::Found { .. }));
+            // Nothing to average until there are two blocks
+            let averaged = blockchain.chain.len() > 2;
+            assert!(matches!(&recorder.0[0], MiningEvent::Retargeted { average_block_time, from: f, to }
+                             if average_block_time.is_some() == averaged && *f == from && *to == tip.target));
+            assert!(matches!(&recorder.0[1], MiningEvent::Started { index, target, miners: None }
+                             if *index == tip.index && *target == tip.target));
+            assert!(matches!(recorder.0.last(), Some(MiningEvent::Found { hash, .. }) if *hash == tip.hash));
+        }
+        // Blocks seconds apart are far too fast for a 10 second target
+        assert!(blockchain.chain[3].target.as_f64() < blockchain.chain[2].target.as_f64());
+    }
+
+    #[test]
+    fn a_competition_names_its_winner_before_the_block() {
+        let mut blockchain = Blockchain::new(&mut ());
+        let mut recorder = Recorder::default();
+        let outcome = blockchain.block_competition("race".to_string(), 0, 3, &MiningLimits::none(), &mut recorder);
+        let MiningOutcome::Found(winner) = outcome else { panic!("no winner") };
+
+        let events = &recorder.0;
+        assert!(matches!(events[1], MiningEvent::Started { miners: Some(3), .. }));
+        let won = &events[events.len() - 2];
+        assert!(matches!(won, MiningEvent::MinerWon { miner, attempts_per_miner } if *miner == winner && attempts_per_miner.len() == 3));
+        assert_run(&[&events[..events.len() - 2], &events[events.len() - 1..]].concat(),
+                   |event| matches!(event, MiningEvent::Found { .. }));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::error::Result;
+use crate::target::Target;
+use crate::{Blockchain, MiningMethod};
+use serde::Serialize;
+use std::fs;
+
+// One row per block, for charting outside the simulator
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockRecord {
+    pub height: u64,
+    pub timestamp: String,
+    // Seconds since the previous block; none for genesis
+    pub timestamp_delta: Option<i64>,
+    pub difficulty: f64,
+    pub bits: String,
+    // Mining figures are missing for genesis and blocks mined elsewhere
+    pub attempts: Option<u64>,
+    pub mining_seconds: Option<f64>,
+    pub hash_rate: Option<f64>,
+    pub method: Option<MiningMethod>,
+    pub miner: String,
+}
+
+// A change of target between two consecutive blocks
+#[derive(Debug, Clone, Serialize)]
+pub struct AdjustmentRecord {
+    pub height: u64,
+    pub timestamp: String,
+    pub from_difficulty: f64,
+    pub to_difficulty: f64,
+    // Above 1 means harder; none if the new target is zero, which no hash meets
+    pub factor: Option<f64>,
+    // "manual" for set_difficulty, otherwise the retarget algorithm's name
+    pub cause: String,
+}
+
+pub fn block_records(blockchain: &Blockchain) -> Vec<BlockRecord> {
+    blockchain.chain
+        .iter()
+        .enumerate()
+        .map(|(height, block)| {
+            // Only blocks this node mined itself have stats
+            let stats = blockchain.stats_for(&block.hash);
+            let timestamp_delta = height
+                .checked_sub(1)
+                .map(|previous| (block.timestamp - blockchain.chain[previous].timestamp).num_seconds());
+            BlockRecord {
+                height: block.index,
+                timestamp: block.timestamp.to_rfc3339(),
+                timestamp_delta,
+                difficulty: block.target.difficulty(),
+                bits: block.target.to_string(),
+                attempts: stats.map(|(stats, _)| stats.attempts),
+                mining_seconds: stats.map(|(stats, _)| stats.total_time.as_secs_f64()),
+                hash_rate: stats
+                    .filter(|(stats, _)| !stats.total_time.is_zero())
+                    .map(|(stats, _)| stats.attempts as f64 / stats.total_time.as_secs_f64()),
+                method: stats.map(|(_, method)| method.clone()),
+                miner: block.coinbase.miner.clone(),
+            }
+        })
+        .collect()
+}
+
+fn adjustment(height: u64, timestamp: &str, from: Target, to: Target, cause: String) -> AdjustmentRecord {
+    AdjustmentRecord {
+        height,
+        timestamp: timestamp.to_string(),
+        from_difficulty: from.difficulty(),
+        to_difficulty: to.difficulty(),
+        factor: (to.as_f64() > 0.0).then(|| from.as_f64() / to.as_f64()),
+        cause,
+    }
+}
+
+pub fn adjustment_records(blockchain: &Blockchain) -> Vec<AdjustmentRecord> {
+    let mut records = Vec::new();
+    for (height, pair) in blockchain.chain.windows(2).enumerate() {
+        let height = height as u64 + 1;
+        let block = &pair[1];
+        let timestamp = block.timestamp.to_rfc3339();
+        let mut from = pair[0].target;
+
+        // A manual change comes first and the retarget then starts from it
+        if let Some(manual) = block.manual_target {
+            if manual != from {
+                records.push(adjustment(height, &timestamp, from, manual, "manual".to_string()));
+            }
+            from = manual;
+        }
+        if block.target != from {
+            let retarget = Blockchain::retarget_at(&blockchain.chain, height as usize);
+            records.push(adjustment(height, &timestamp, from, block.target, retarget.name()));
+        }
+    }
+    records
+}
+
+// Quotes a field if it would otherwise break the CSV
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn blocks_csv(records: &[BlockRecord]) -> String {
+    let mut csv = String::from("height,timestamp,timestamp_delta,difficulty,bits,attempts,mining_seconds,hash_rate,method,miner\n");
+    for record in records {
+        let method = record.method.as_ref().map(|method| format!("{:?}", method));
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            record.height,
+            record.timestamp,
+            optional(record.timestamp_delta),
+            record.difficulty,
+            record.bits,
+            optional(record.attempts),
+            optional(record.mining_seconds),
+            optional(record.hash_rate),
+            optional(method),
+            csv_field(&record.miner)
+        ));
+    }
+    csv
+}
+
+fn adjustments_csv(records: &[AdjustmentRecord]) -> String {
+    let mut csv = String::from("height,timestamp,from_difficulty,to_difficulty,factor,cause\n");
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            record.height,
+            record.timestamp,
+            record.from_difficulty,
+            record.to_difficulty,
+            optional(record.factor),
+            csv_field(&record.cause)
+        ));
+    }
+    csv
+}
+
+// Writes <prefix>_blocks.{csv,json} and <prefix>_difficulty.{csv,json};
+// returns the paths written
+pub fn export(blockchain: &Blockchain, prefix: &str) -> Result<Vec<String>> {
+    let blocks = block_records(blockchain);
+    let adjustments = adjustment_records(blockchain);
+    let files = [
+        (format!("{}_blocks.csv", prefix), blocks_csv(&blocks)),
+        (format!("{}_blocks.json", prefix), serde_json::to_string_pretty(&blocks)?),
+        (format!("{}_difficulty.csv", prefix), adjustments_csv(&adjustments)),
+        (format!("{}_difficulty.json", prefix), serde_json::to_string_pretty(&adjustments)?),
+    ];
+
+    let mut written = Vec::new();
+    for (path, content) in files {
+        fs::write(&path, content)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{MiningLimits, MiningOutcome};
+    use crate::InsertOutcome;
+
+    fn mine(blockchain: &mut Blockchain) {
+        let outcome = blockchain.add_mined_block("test".to_string(), 0, &MiningLimits::none(), &mut ());
+        assert!(matches!(outcome, MiningOutcome::Found(_)));
+    }
+
+    #[test]
+    fn mining_stats_stay_with_their_block_through_a_reorg() {
+        let mut blockchain = Blockchain::new(&mut ());
+        mine(&mut blockchain);
+        let genesis = blockchain.chain[0].hash.clone();
+        let rival = crate::tests::mine_on(&blockchain, &genesis, "Rival");
+        let rival_hash = rival.hash.clone();
+        blockchain.receive_block(rival).unwrap();
+        let heavier = crate::tests::mine_on(&blockchain, &rival_hash, "Rival");
+        assert_eq!(blockchain.receive_block(heavier).unwrap(), InsertOutcome::Reorg { depth: 1 });
+        mine(&mut blockchain);
+
+        let records = block_records(&blockchain);
+        assert_eq!(records.len(), 4);
+        // Our first block was orphaned; the rival's two have no stats here
+        for record in &records[..3] {
+            assert_eq!(record.attempts, None, "height {}", record.height);
+        }
+        assert_eq!(records[1].miner, "Rival");
+        let (ours, _) = &blockchain.mining_stats[1];
+        assert_eq!(records[3].attempts, Some(ours.attempts));
+        assert!(matches!(records[3].method, Some(MiningMethod::Normal)));
+    }
+
+    #[test]
+    fn a_zero_target_has_no_factor() {
+        let from = Target::from_leading_zeros(2);
+        assert_eq!(adjustment(1, "now", from, Target::from_bits(0), "manual".to_string()).factor, None);
+        let factor = adjustment(1, "now", from, from.scale(0.5), "manual".to_string()).factor.unwrap();
+        assert!((factor - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn csv_fields_with_commas_quotes_or_newlines_are_quoted() {
+        assert_eq!(csv_field("Miner 1"), "Miner 1");
+        assert_eq!(csv_field("Smith, Jones"), "\"Smith, Jones\"");
+        assert_eq!(csv_field("the \"best\" miner"), "\"the \"\"best\"\" miner\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+
+        let mut blockchain = Blockchain::new(&mut ());
+        mine(&mut blockchain);
+        let mut records = block_records(&blockchain);
+        records[1].miner = "Smith, \"Jr\"".to_string();
+        let csv = blocks_csv(&records);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].ends_with(",Normal,\"Smith, \"\"Jr\"\"\""), "{}", lines[2]);
+        // Genesis leaves its mining figures empty
+        assert!(lines[1].contains(",,,,"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn json_records_have_the_documented_shape() {
+        let mut blockchain = Blockchain::new(&mut ());
+        mine(&mut blockchain);
+        blockchain.set_difficulty(3).unwrap();
+        mine(&mut blockchain);
+
+        let blocks = serde_json::to_value(block_records(&blockchain)).unwrap();
+        let blocks = blocks.as_array().unwrap();
+        assert_eq!(blocks.len(), 3);
+        let mut keys: Vec<&str> = blocks[0].as_object().unwrap().keys().map(String::as_str).collect();
+        let mut expected = vec!["height", "timestamp", "timestamp_delta", "difficulty", "bits", "attempts",
+                                "mining_seconds", "hash_rate", "method", "miner"];
+        keys.sort();
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert!(blocks[0]["timestamp_delta"].is_null() && blocks[0]["attempts"].is_null());
+        assert_eq!(blocks[1]["height"], 1);
+        assert_eq!(blocks[1]["method"], "Normal");
+        assert!(blocks[1]["attempts"].is_u64());
+
+        let adjustments = serde_json::to_value(adjustment_records(&blockchain)).unwrap();
+        let manual = &adjustments.as_array().unwrap()[0];
+        assert_eq!(manual["height"], 2);
+        assert_eq!(manual["cause"], "manual");
+        // Harder, so above 1
+        assert!(manual["factor"].as_f64().unwrap() > 1.0);
+    }
+}
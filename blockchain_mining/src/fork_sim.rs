@@ -0,0 +1,222 @@
+use crate::block_tree::{BlockTree, InsertOutcome};
+use crate::target::{to_hex, Target};
+use crate::Block;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct ForkSimConfig {
+    pub miners: u32,
+    // Stop once the best chain has grown by this many blocks
+    pub blocks: u64,
+    // Expected blocks found per round across all miners. Everything found in
+    // the same round races, so higher values mean more forks.
+    pub blocks_per_round: f64,
+}
+
+// A miner with its own view of the block tree, hashing on its best tip
+pub struct SimMiner {
+    pub id: u32,
+    pub tree: BlockTree,
+    pub candidate: Block,
+    nonce: u64,
+}
+
+impl SimMiner {
+    pub fn new(id: u32, genesis: Block, target: Target) -> Self {
+        let tree = BlockTree::new(genesis);
+        let candidate = Self::candidate_on(id, &tree, target);
+        SimMiner { id, tree, candidate, nonce: 0 }
+    }
+
+    fn candidate_on(id: u32, tree: &BlockTree, target: Target) -> Block {
+        let tip = &tree.tip().block;
+        Block::new(tip.index + 1, format!("Block by miner {}", id), tip.hash.clone(), target)
+    }
+
+    // Start over on top of the current best tip if it moved
+    pub fn refresh_candidate(&mut self) {
+        if self.candidate.previous_hash != self.tree.tip().block.hash {
+            self.candidate = Self::candidate_on(self.id, &self.tree, self.candidate.target);
+        }
+    }
+
+    // Hash up to `attempts` nonces; returns the block if one met the target
+    pub fn try_mine(&mut self, attempts: u64) -> Option<Block> {
+        for _ in 0..attempts {
+            self.candidate.nonce = self.nonce;
+            self.nonce = self.nonce.wrapping_add(1);
+
+            let hash_bytes = self.candidate.calculate_hash();
+            if self.candidate.target.is_met_by(&hash_bytes) {
+                self.candidate.hash = to_hex(&hash_bytes);
+                let block = self.candidate.clone();
+                self.tree.insert(block.clone()).expect("own block is valid");
+                self.refresh_candidate();
+                return Some(block);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ForkSimReport {
+    pub rounds: u64,
+    pub blocks_mined: usize,
+    pub best_height: u64,
+    pub orphans: usize,
+    pub reorgs: u64,
+    pub max_reorg_depth: u64,
+    // (blocks in the best chain, orphaned blocks) per miner
+    pub per_miner: Vec<(usize, usize)>,
+}
+
+impl ForkSimReport {
+    pub fn orphan_rate(&self) -> f64 {
+        self.orphans as f64 / self.blocks_mined.max(1) as f64
+    }
+
+    pub fn display(&self) {
+        println!("📏 Best chain height: {}", self.best_height);
+        println!("⛏️  Blocks mined: {} in {} rounds", self.blocks_mined, self.rounds);
+        println!("🥀 Orphaned blocks: {} ({:.1}%)", self.orphans, self.orphan_rate() * 100.0);
+        println!("🔀 Reorgs seen by miners: {} (deepest: {} blocks)", self.reorgs, self.max_reorg_depth);
+        for (i, (in_chain, orphaned)) in self.per_miner.iter().enumerate() {
+            println!("   Miner {}: {} blocks in best chain, {} orphaned", i + 1, in_chain, orphaned);
+        }
+    }
+}
+
+// Every miner hashes a random number of nonces per round, then all blocks
+// found that round are broadcast. Each miner hears its own block first (the
+// rest in random order) and keeps the first one seen on a tie, so blocks
+// found in the same round fork the chain until one branch gets ahead.
+pub fn simulate(genesis: &Block, config: &ForkSimConfig) -> ForkSimReport {
+    simulate_with(genesis, config, &mut rand::thread_rng())
+}
+
+pub fn simulate_with(genesis: &Block, config: &ForkSimConfig, rng: &mut impl Rng) -> ForkSimReport {
+    let (min_attempts, max_attempts) = (200u64, 1000u64);
+    let hashes_per_round = config.miners as f64 * (min_attempts + max_attempts) as f64 / 2.0;
+    let target = Target::from_difficulty(hashes_per_round / config.blocks_per_round);
+
+    let mut miners: Vec<SimMiner> = (1..=config.miners)
+        .map(|id| SimMiner::new(id, genesis.clone(), target))
+        .collect();
+    let mut found_by: HashMap<String, u32> = HashMap::new();
+    let mut report = ForkSimReport::default();
+
+    while miners[0].tree.tip().height < genesis.index + config.blocks {
+        report.rounds += 1;
+
+        let mut found = Vec::new();
+        for miner in miners.iter_mut() {
+            let attempts = rng.gen_range(min_attempts..max_attempts);
+            if let Some(block) = miner.try_mine(attempts) {
+                found_by.insert(block.hash.clone(), miner.id);
+                found.push(block);
+            }
+        }
+
+        for miner in miners.iter_mut() {
+            // Blocks arrive in random order, except that our own came first
+            found.shuffle(rng);
+            for block in &found {
+                match miner.tree.insert(block.clone()).expect("broadcast block is valid") {
+                    InsertOutcome::Reorg { depth } => {
+                        report.reorgs += 1;
+                        report.max_reorg_depth = report.max_reorg_depth.max(depth);
+                    }
+                    InsertOutcome::Extended | InsertOutcome::SideChain | InsertOutcome::Duplicate => {}
+                }
+            }
+            miner.refresh_candidate();
+        }
+        report.blocks_mined += found.len();
+    }
+
+    // Everyone has heard every block, so any miner's tree will do
+    let tree = &miners[0].tree;
+    report.best_height = tree.tip().height;
+    report.orphans = tree.orphans().len();
+    report.per_miner = (1..=config.miners)
+        .map(|id| {
+            let count = |blocks: Vec<&Block>| {
+                blocks.iter().filter(|b| found_by.get(&b.hash) == Some(&id)).count()
+            };
+            (count(tree.best_chain()), count(tree.orphans()))
+        })
+        .collect();
+    debug_assert_eq!(tree.block_count(), report.blocks_mined + 1);
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pow::PowAlgorithm;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn genesis() -> Block {
+        Block::genesis(PowAlgorithm::default(), &mut ())
+    }
+
+    #[test]
+    fn a_tie_resolves_once_one_branch_gets_ahead() {
+        let genesis = genesis();
+        let mut a = SimMiner::new(1, genesis.clone(), Target::max());
+        let mut b = SimMiner::new(2, genesis, Target::max());
+
+        // Both find height 1 at once and keep their own block
+        let a1 = a.try_mine(1).expect("max target is met by any hash");
+        let b1 = b.try_mine(1).expect("max target is met by any hash");
+        assert_eq!(a.tree.insert(b1.clone()).unwrap(), InsertOutcome::SideChain);
+        assert_eq!(b.tree.insert(a1.clone()).unwrap(), InsertOutcome::SideChain);
+        assert_ne!(a.tree.tip().block.hash, b.tree.tip().block.hash);
+
+        // a extends its branch and b switches over
+        b.refresh_candidate();
+        let a2 = a.try_mine(1).expect("max target is met by any hash");
+        assert_eq!(a2.previous_hash, a1.hash);
+        assert_eq!(b.tree.insert(a2.clone()).unwrap(), InsertOutcome::Reorg { depth: 1 });
+        b.refresh_candidate();
+
+        assert_eq!(a.tree.tip().block.hash, a2.hash);
+        assert_eq!(b.tree.tip().block.hash, a2.hash);
+        assert_eq!(b.candidate.previous_hash, a2.hash);
+        assert_eq!(b.tree.orphans().len(), 1);
+    }
+
+    #[test]
+    fn every_mined_block_ends_up_in_the_best_chain_or_orphaned() {
+        let config = ForkSimConfig { miners: 3, blocks: 15, blocks_per_round: 1.5 };
+        let report = simulate_with(&genesis(), &config, &mut StdRng::seed_from_u64(3));
+
+        assert_eq!(report.best_height, config.blocks);
+        assert_eq!(report.blocks_mined, report.best_height as usize + report.orphans);
+        let in_chain: usize = report.per_miner.iter().map(|(in_chain, _)| in_chain).sum();
+        let orphaned: usize = report.per_miner.iter().map(|(_, orphaned)| orphaned).sum();
+        assert_eq!(in_chain, config.blocks as usize);
+        assert_eq!(orphaned, report.orphans);
+        // At 1.5 blocks a round some rounds must race
+        assert!(report.orphans > 0);
+        assert!(report.reorgs > 0);
+    }
+
+    #[test]
+    fn more_blocks_per_round_means_more_orphans() {
+        let genesis = genesis();
+        let mut rng = StdRng::seed_from_u64(11);
+        let orphan_rate = |blocks_per_round: f64, rng: &mut StdRng| {
+            let config = ForkSimConfig { miners: 4, blocks: 25, blocks_per_round };
+            simulate_with(&genesis, &config, rng).orphan_rate()
+        };
+        let rare = orphan_rate(0.2, &mut rng);
+        let frequent = orphan_rate(2.0, &mut rng);
+        assert!(rare < frequent, "{} vs {}", rare, frequent);
+    }
+}
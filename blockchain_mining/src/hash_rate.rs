@@ -0,0 +1,183 @@
+use crate::retarget::{BlockTime, RetargetAlgorithm};
+use crate::retarget_sim::{self, HashRateShock, RetargetSimConfig};
+
+// Two-sided 95% intervals
+const Z_95: f64 = 1.959964;
+
+// The network hash rate over the `blocks` blocks ending at `height`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashRateEstimate {
+    pub height: usize,
+    pub blocks: usize,
+    pub hash_rate: f64,
+    // 95% confidence interval
+    pub low: f64,
+    pub high: f64,
+}
+
+// Needs nothing but timestamps and targets, so it works on any chain.
+//
+// At a steady hash rate H, a block of difficulty D takes an exponential time
+// with mean D / H, so solve_time / D is exponential with rate H whatever the
+// difficulty. Summed over n blocks that gives S with 2·H·S following a
+// chi-squared distribution with 2n degrees of freedom: (n - 1) / S estimates H
+// without bias and the chi-squared quantiles give an exact interval.
+//
+// `history[0]` is genesis; blocks (height - blocks, height] are used.
+pub fn estimate(history: &[BlockTime], height: usize, blocks: usize) -> Option<HashRateEstimate> {
+    if blocks < 2 || height >= history.len() || height < blocks {
+        return None;
+    }
+    let work_time: f64 = (height - blocks + 1..=height)
+        .map(|i| (history[i].timestamp - history[i - 1].timestamp) as f64 / history[i].target.difficulty())
+        .sum();
+    // Timestamps may go backwards a little; over too short a window that can
+    // leave no measurable time at all
+    if work_time <= 0.0 {
+        return None;
+    }
+    let degrees = 2.0 * blocks as f64;
+    Some(HashRateEstimate {
+        height,
+        blocks,
+        hash_rate: (blocks - 1) as f64 / work_time,
+        low: chi_squared_quantile(degrees, -Z_95) / (2.0 * work_time),
+        high: chi_squared_quantile(degrees, Z_95) / (2.0 * work_time),
+    })
+}
+
+// Every window of `blocks` blocks, oldest first
+pub fn sliding(history: &[BlockTime], blocks: usize) -> Vec<HashRateEstimate> {
+    (blocks..history.len()).filter_map(|height| estimate(history, height, blocks)).collect()
+}
+
+// Wilson–Hilferty: the cube root of a chi-squared variable is close to
+// normal, within a fraction of a percent from a handful of blocks up
+fn chi_squared_quantile(degrees: f64, z: f64) -> f64 {
+    let spread = 2.0 / (9.0 * degrees);
+    degrees * (1.0 - spread + z * spread.sqrt()).powi(3)
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    pub blocks: usize,
+    pub windows: usize,
+    // Share of intervals that contain the true hash rate; should be near 95%
+    pub coverage: f64,
+    pub median_error: f64,
+}
+
+// Estimates over non-overlapping windows of a simulated chain whose hash rate
+// is known, skipping windows that straddle a hash-rate shock
+pub fn validate(config: &RetargetSimConfig, history: &[BlockTime], blocks: usize) -> ValidationReport {
+    let mut errors = Vec::new();
+    let mut covered = 0;
+    for height in (blocks..history.len()).step_by(blocks) {
+        let actual = config.hash_rate_at(height);
+        if config.hash_rate_at(height - blocks + 1) != actual {
+            continue;
+        }
+        if let Some(estimate) = estimate(history, height, blocks) {
+            errors.push((estimate.hash_rate - actual).abs() / actual);
+            if (estimate.low..=estimate.high).contains(&actual) {
+                covered += 1;
+            }
+        }
+    }
+    errors.sort_by(f64::total_cmp);
+    ValidationReport {
+        blocks,
+        windows: errors.len(),
+        coverage: covered as f64 / errors.len().max(1) as f64,
+        median_error: errors.get(errors.len() / 2).copied().unwrap_or(0.0),
+    }
+}
+
+pub fn display_estimates(history: &[BlockTime], blocks: usize, local_hash_rate: Option<f64>) {
+    println!("\n🌐 NETWORK HASH RATE, estimated from timestamps and difficulty over {} blocks", blocks);
+    println!("───────────────────────────");
+    let estimates = sliding(history, blocks);
+    if estimates.is_empty() {
+        println!("Not enough blocks: need more than {}, the chain has {}.", blocks, history.len());
+    } else {
+        // Around 15 rows, always including the latest window
+        let step = estimates.len().div_ceil(15);
+        let last = estimates.len() - 1;
+        for (i, estimate) in estimates.iter().enumerate() {
+            if i % step == 0 || i == last {
+                println!("   Blocks {:>5}–{:<5} {:>12.0} H/s  (95%: {:.0} – {:.0})",
+                         estimate.height - blocks + 1, estimate.height, estimate.hash_rate,
+                         estimate.low, estimate.high);
+            }
+        }
+    }
+    let span = history.len().saturating_sub(1).min(blocks);
+    let seconds = history.last().zip(history.get(history.len().saturating_sub(span + 1)))
+        .map_or(0, |(last, first)| last.timestamp - first.timestamp);
+    if span > 0 && (seconds as f64) < 2.0 * span as f64 {
+        println!("⚠️  Blocks are only a second or so apart; with whole-second timestamps these estimates are rough");
+    }
+    if let Some(rate) = local_hash_rate {
+        println!("💻 Measured by this machine while mining: {:.0} H/s", rate);
+    }
+    println!("───────────────────────────\n");
+}
+
+pub fn display_validation(algorithm: RetargetAlgorithm, target_time: u64) {
+    let config = RetargetSimConfig {
+        blocks: 3000,
+        target_time,
+        base_hash_rate: 1_000_000.0,
+        shocks: vec![
+            HashRateShock { at_block: 1000, multiplier: 10.0 },
+            HashRateShock { at_block: 2000, multiplier: 0.2 },
+        ],
+    };
+    let result = retarget_sim::simulate(algorithm, &config);
+
+    println!("🧪 Checked against a simulated chain with a known hash rate ({}, {} blocks, x10 at block 1000, x0.2 at 2000):",
+             algorithm.name(), config.blocks);
+    for blocks in [10, 20, 50, 100] {
+        let report = validate(&config, &result.history, blocks);
+        println!("   {:>3}-block windows: true rate inside the 95% interval {:>5.1}% of the time, median error {:>5.1}% ({} windows)",
+                 report.blocks, report.coverage * 100.0, report.median_error * 100.0, report.windows);
+    }
+    println!("Wider windows are more precise but are slower to notice a change.\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeded_sim::{self, SeededSimConfig};
+
+    #[test]
+    fn intervals_cover_the_hash_rate_of_seeded_simulations() {
+        // A 95% interval misses now and then, so look at several runs
+        let covered = (1..=10)
+            .filter(|&seed| {
+                let config = SeededSimConfig {
+                    seed,
+                    hash_rates: vec![5.0, 15.0],
+                    blocks: 120,
+                    target_time: 10,
+                    retarget: RetargetAlgorithm::Lwma { window: 20 },
+                    halving_interval: 100,
+                };
+                let history = seeded_sim::run(&config).blockchain.block_times();
+                // Skip the first blocks, where the retarget climbs from genesis
+                let estimate = estimate(&history, 120, 100).expect("enough blocks");
+                estimate.low <= 20.0 && 20.0 <= estimate.high
+            })
+            .count();
+        assert!(covered >= 8, "only {} of 10 intervals contain the true hash rate", covered);
+    }
+
+    #[test]
+    fn chi_squared_quantiles_match_tables() {
+        // 2.5% and 97.5% points for 20 and 100 degrees of freedom
+        assert!((chi_squared_quantile(20.0, -Z_95) - 9.591).abs() < 0.05);
+        assert!((chi_squared_quantile(20.0, Z_95) - 34.170).abs() < 0.05);
+        assert!((chi_squared_quantile(100.0, -Z_95) - 74.222).abs() < 0.05);
+        assert!((chi_squared_quantile(100.0, Z_95) - 129.561).abs() < 0.05);
+    }
+}
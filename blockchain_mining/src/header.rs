@@ -0,0 +1,136 @@
+use crate::pow::ProofOfWork;
+use crate::target::{to_hex, Target};
+use crate::Block;
+use sha2::{Digest, Sha256};
+use std::time::Instant;
+
+// Fast path for `Block::calculate_hash`. The header is serialized once and
+// everything before the nonce is fed to SHA-256 up front; each attempt clones
+// that midstate and hashes only the nonce tail. Produces exactly the same
+// hash as the slow path, so blocks mined either way validate the same.
+pub struct HeaderHasher {
+    midstate: Sha256,
+    // Everything after the extra nonce
+    suffix: Vec<u8>,
+    pow: Box<dyn ProofOfWork>,
+}
+
+impl HeaderHasher {
+    // Must be rebuilt whenever anything but the nonce changes, including the
+    // extra nonce and timestamp when the nonce rolls over
+    pub fn new(block: &Block) -> Self {
+        let prefix = format!("{}{}{}{}", block.index, block.timestamp.timestamp(), block.data, block.previous_hash);
+        let suffix = format!(
+            "{}{}{}{}{}",
+            block.target.bits(),
+            block.coinbase.miner,
+            block.coinbase.subsidy,
+            block.coinbase.fees,
+            block.overrides()
+        );
+        let mut midstate = Sha256::new();
+        midstate.update(prefix.as_bytes());
+        HeaderHasher { midstate, suffix: suffix.into_bytes(), pow: block.pow.proof_of_work() }
+    }
+
+    pub fn hash(&self, nonce: u64, extra_nonce: u64) -> [u8; 32] {
+        let mut digits = [0u8; 20];
+        let mut hasher = self.midstate.clone();
+        hasher.update(write_decimal(nonce, &mut digits));
+        hasher.update(b":");
+        hasher.update(write_decimal(extra_nonce, &mut digits));
+        hasher.update(&self.suffix);
+        self.pow.finish(hasher.finalize().into())
+    }
+}
+
+// Formats `n` into the end of `buf` without allocating
+fn write_decimal(mut n: u64, buf: &mut [u8; 20]) -> &[u8] {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            return &buf[start..];
+        }
+    }
+}
+
+// Hashes per second of each way of checking a nonce, on a target nobody meets
+pub fn display_benchmark(attempts: u64) {
+    let template = Block::new(1, "Benchmark block".to_string(), "0".repeat(64), Target::from_leading_zeros(16));
+    let leading_zeros = "0".repeat(16);
+
+    let measure = |name: &str, run: &dyn Fn() -> u64| {
+        let start = Instant::now();
+        let found = run();
+        let hps = attempts as f64 / start.elapsed().as_secs_f64().max(f64::EPSILON);
+        // `found` keeps the loop from being optimized away
+        println!("   {:<40} {:>12.0} H/s{}", name, hps, if found > 0 { " (found a block!)" } else { "" });
+        hps
+    };
+
+    println!("\n🏎️  HASHING BENCHMARK ({} attempts each)", attempts);
+    println!("───────────────────────────");
+    let baseline = measure("format + hash + hex starts_with", &|| {
+        let mut block = template.clone();
+        let mut found = 0;
+        for nonce in 0..attempts {
+            block.nonce = nonce;
+            if to_hex(&block.calculate_hash()).starts_with(&leading_zeros) {
+                found += 1;
+            }
+        }
+        found
+    });
+    let bytes = measure("format + hash + byte compare", &|| {
+        let mut block = template.clone();
+        let mut found = 0;
+        for nonce in 0..attempts {
+            block.nonce = nonce;
+            if block.target.is_met_by(&block.calculate_hash()) {
+                found += 1;
+            }
+        }
+        found
+    });
+    let midstate = measure("cached midstate + byte compare", &|| {
+        let header = HeaderHasher::new(&template);
+        (0..attempts)
+            .filter(|&nonce| template.target.is_met_by(&header.hash(nonce, template.extra_nonce)))
+            .count() as u64
+    });
+    println!("───────────────────────────");
+    println!("⚡ Byte compare: {:.2}x, midstate: {:.2}x faster than the original loop\n",
+             bytes / baseline, midstate / baseline);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pow::PowAlgorithm;
+    use crate::retarget::RetargetAlgorithm;
+
+    #[test]
+    fn matches_the_slow_path() {
+        for pow in PowAlgorithm::all() {
+            let mut block = Block::new(7, "some data".to_string(), "ab".repeat(32), Target::from_leading_zeros(3));
+            block.coinbase.miner = "Miner 2".to_string();
+            block.coinbase.subsidy = 5_000_000_000;
+            block.pow = pow;
+            for overridden in [false, true] {
+                if overridden {
+                    block.manual_target = Some(Target::from_leading_zeros(2));
+                    block.retarget = Some(RetargetAlgorithm::Lwma { window: 20 });
+                }
+                let header = HeaderHasher::new(&block);
+                for (nonce, extra_nonce) in [(0, 0), (9, 0), (10, 3), (123_456, 78), (u64::MAX, u64::MAX)] {
+                    block.nonce = nonce;
+                    block.extra_nonce = extra_nonce;
+                    assert_eq!(header.hash(nonce, extra_nonce), block.calculate_hash());
+                }
+            }
+        }
+    }
+}
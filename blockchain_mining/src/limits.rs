@@ -0,0 +1,150 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// Shared flag another thread can set to stop a mining run
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// When to give up on a mining run. The default never gives up.
+#[derive(Debug, Clone, Default)]
+pub struct MiningLimits {
+    pub cancel: Option<CancelToken>,
+    pub deadline: Option<Instant>,
+    pub max_attempts: Option<u64>,
+}
+
+impl MiningLimits {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    // Checked on every hash, so keep it cheap
+    pub fn attempts_exhausted(&self, attempts: u64) -> bool {
+        self.max_attempts.is_some_and(|max| attempts >= max)
+    }
+
+    // Cancellation and the clock; checked every few thousand hashes
+    pub fn check(&self) -> Option<StopReason> {
+        if self.cancel.as_ref().is_some_and(|token| token.is_cancelled()) {
+            return Some(StopReason::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Some(StopReason::DeadlineReached);
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Cancelled,
+    DeadlineReached,
+    AttemptsExhausted,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Cancelled => write!(f, "cancelled"),
+            StopReason::DeadlineReached => write!(f, "time limit reached"),
+            StopReason::AttemptsExhausted => write!(f, "attempt budget used up"),
+        }
+    }
+}
+
+// Result of a bounded mining run: either the block was found or we stopped
+// early, with the work done so far either way
+#[derive(Debug, Clone)]
+pub enum MiningOutcome<T> {
+    Found(T),
+    NotFound { reason: StopReason, attempts: u64 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::Target;
+    use crate::Block;
+    use std::thread;
+    use std::time::Duration;
+
+    // 128 leading zero bits: no run in a test will ever find this block
+    fn unminable() -> Block {
+        Block::new(1, "never found".to_string(), "0".repeat(64), Target::from_leading_zeros(32))
+    }
+
+    fn stop_reason(limits: &MiningLimits) -> (StopReason, u64) {
+        match unminable().mine(limits, &mut ()) {
+            MiningOutcome::NotFound { reason, attempts } => (reason, attempts),
+            MiningOutcome::Found(_) => panic!("an unminable block was found"),
+        }
+    }
+
+    #[test]
+    fn no_limits_never_stop() {
+        let limits = MiningLimits::none();
+        assert_eq!(limits.check(), None);
+        assert!(!limits.attempts_exhausted(u64::MAX));
+    }
+
+    #[test]
+    fn the_attempt_budget_stops_on_the_exact_attempt() {
+        let limits = MiningLimits { max_attempts: Some(1_000), ..MiningLimits::none() };
+        assert!(!limits.attempts_exhausted(999));
+        assert!(limits.attempts_exhausted(1_000));
+        assert_eq!(stop_reason(&limits), (StopReason::AttemptsExhausted, 1_000));
+    }
+
+    #[test]
+    fn a_passed_deadline_stops_the_run() {
+        let limits = MiningLimits { deadline: Some(Instant::now()), ..MiningLimits::none() };
+        assert_eq!(limits.check(), Some(StopReason::DeadlineReached));
+        let (reason, attempts) = stop_reason(&limits);
+        assert_eq!(reason, StopReason::DeadlineReached);
+        // The clock is only read every 4,096 hashes
+        assert_eq!(attempts, 4_096);
+
+        let later = MiningLimits { deadline: Instant::now().checked_add(Duration::from_secs(3600)), ..MiningLimits::none() };
+        assert_eq!(later.check(), None);
+    }
+
+    #[test]
+    fn cancelling_from_another_thread_stops_the_run() {
+        let token = CancelToken::new();
+        let limits = MiningLimits { cancel: Some(token.clone()), ..MiningLimits::none() };
+        assert_eq!(limits.check(), None);
+
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+        let (reason, attempts) = stop_reason(&limits);
+        canceller.join().unwrap();
+        assert_eq!(reason, StopReason::Cancelled);
+        assert!(attempts.is_multiple_of(4_096));
+    }
+
+    #[test]
+    fn cancellation_wins_over_a_passed_deadline() {
+        let token = CancelToken::new();
+        token.cancel();
+        let limits = MiningLimits { cancel: Some(token), deadline: Some(Instant::now()), max_attempts: None };
+        assert_eq!(limits.check(), Some(StopReason::Cancelled));
+    }
+}
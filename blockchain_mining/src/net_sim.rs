@@ -0,0 +1,219 @@
+use crate::fork_sim::SimMiner;
+use crate::target::Target;
+use crate::Block;
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Debug, Clone)]
+pub struct NetSimConfig {
+    pub miners: u32,
+    pub target_time: f64,
+    // Mean one-way delay before a block reaches another miner, in seconds
+    pub latency: f64,
+    // Each delivery is delayed by latency ± up to this much, uniformly
+    pub jitter: f64,
+    // Stop after this many blocks have been found in total
+    pub blocks: usize,
+}
+
+enum EventKind {
+    // The miner finds a block on whatever tip it has right now
+    Found { miner: usize },
+    // A block reaches a miner
+    Deliver { miner: usize, block: Block },
+}
+
+struct Event {
+    time: f64,
+    seq: u64,
+    kind: EventKind,
+}
+
+// BinaryHeap is a max-heap: order so the earliest event pops first, ties in
+// scheduling order
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.time.total_cmp(&self.time).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Event {}
+
+#[derive(Default)]
+struct EventQueue {
+    heap: BinaryHeap<Event>,
+    seq: u64,
+}
+
+impl EventQueue {
+    fn schedule(&mut self, time: f64, kind: EventKind) {
+        self.seq += 1;
+        self.heap.push(Event { time, seq: self.seq, kind });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NetSimReport {
+    pub blocks_found: usize,
+    pub stale_blocks: usize,
+}
+
+impl NetSimReport {
+    pub fn stale_rate(&self) -> f64 {
+        self.stale_blocks as f64 / self.blocks_found.max(1) as f64
+    }
+}
+
+// Discrete-event simulation: block discovery is a Poisson process per miner
+// (equal hash power, network rate of one block per target_time) and blocks
+// take latency ± jitter to reach every other miner. Until a block arrives the
+// miner keeps extending its stale tip, which is what produces forks.
+pub fn simulate(genesis: &Block, config: &NetSimConfig) -> NetSimReport {
+    simulate_with(genesis, config, &mut rand::thread_rng())
+}
+
+pub fn simulate_with(genesis: &Block, config: &NetSimConfig, rng: &mut impl Rng) -> NetSimReport {
+    // Timing comes from the event model, so proof of work only needs to be
+    // real, not hard
+    let target = Target::max();
+    let mean_interval = config.target_time * config.miners as f64;
+
+    let mut miners: Vec<SimMiner> = (1..=config.miners)
+        .map(|id| SimMiner::new(id, genesis.clone(), target))
+        .collect();
+    // Blocks that arrived before their parent did
+    let mut waiting: Vec<Vec<Block>> = vec![Vec::new(); miners.len()];
+
+    let mut queue = EventQueue::default();
+    for miner in 0..miners.len() {
+        let delay = -mean_interval * (1.0 - rng.gen::<f64>()).ln();
+        queue.schedule(delay, EventKind::Found { miner });
+    }
+
+    let mut blocks_found = 0;
+
+    while let Some(event) = queue.heap.pop() {
+        match event.kind {
+            EventKind::Found { miner } => {
+                if blocks_found >= config.blocks {
+                    // Stop mining, but let in-flight blocks land
+                    continue;
+                }
+                let block = loop {
+                    if let Some(block) = miners[miner].try_mine(1_000) {
+                        break block;
+                    }
+                };
+                blocks_found += 1;
+
+                for other in (0..miners.len()).filter(|other| *other != miner) {
+                    let delay = config.latency + config.jitter * rng.gen_range(-1.0..=1.0);
+                    let deliver = EventKind::Deliver { miner: other, block: block.clone() };
+                    queue.schedule(event.time + delay.max(0.0), deliver);
+                }
+
+                let delay = -mean_interval * (1.0 - rng.gen::<f64>()).ln();
+                queue.schedule(event.time + delay, EventKind::Found { miner });
+            }
+            EventKind::Deliver { miner, block } => {
+                waiting[miner].push(block);
+                // Keep connecting until nothing more fits
+                while let Some(pos) = waiting[miner]
+                    .iter()
+                    .position(|b| miners[miner].tree.contains(&b.previous_hash))
+                {
+                    let block = waiting[miner].remove(pos);
+                    miners[miner].tree.insert(block).expect("delivered block is valid");
+                }
+                miners[miner].refresh_candidate();
+            }
+        }
+    }
+
+    let tree = &miners[0].tree;
+    NetSimReport {
+        blocks_found,
+        stale_blocks: tree.orphans().len(),
+    }
+}
+
+// Stale rate for a grid of latencies and block times, next to the chance that
+// one of the other miners finds a block while ours is still in flight
+pub fn display_latency_sweep(genesis: &Block, miners: u32, blocks: usize) {
+    let latencies = [0.0, 0.5, 1.0, 2.0, 5.0];
+    let target_times = [2.0, 10.0, 60.0, 600.0];
+
+    println!("\n📡 STALE BLOCK RATE vs LATENCY ({} miners, {} blocks per run)", miners, blocks);
+    println!("───────────────────────────");
+    print!("{:>12}", "latency \\ T");
+    for target_time in &target_times {
+        print!("{:>18}", format!("{}s", target_time));
+    }
+    println!();
+
+    for latency in &latencies {
+        print!("{:>12}", format!("{}s", latency));
+        for target_time in &target_times {
+            let config = NetSimConfig {
+                miners,
+                target_time: *target_time,
+                latency: *latency,
+                jitter: latency * 0.5,
+                blocks,
+            };
+            let report = simulate(genesis, &config);
+            let others_share = (miners - 1) as f64 / miners as f64;
+            let expected = 1.0 - (-others_share * latency / target_time).exp();
+            print!("{:>18}", format!("{:.1}% (~{:.1}%)", report.stale_rate() * 100.0, expected * 100.0));
+        }
+        println!();
+    }
+    println!("Measured stale rate (rough theoretical value in parentheses)");
+    println!("───────────────────────────\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pow::PowAlgorithm;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn stale_rate(latency: f64, seed: u64) -> f64 {
+        let genesis = Block::genesis(PowAlgorithm::default(), &mut ());
+        let config = NetSimConfig { miners: 5, target_time: 10.0, latency, jitter: 0.0, blocks: 2_000 };
+        simulate_with(&genesis, &config, &mut StdRng::seed_from_u64(seed)).stale_rate()
+    }
+
+    #[test]
+    fn instant_delivery_never_forks() {
+        assert_eq!(stale_rate(0.0, 1), 0.0);
+    }
+
+    #[test]
+    fn the_stale_rate_grows_with_latency() {
+        let rates: Vec<f64> = [0.5, 2.0, 5.0].iter().map(|latency| stale_rate(*latency, 2)).collect();
+        assert!(rates.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", rates);
+    }
+
+    #[test]
+    fn the_stale_rate_is_near_the_chance_of_a_block_in_flight() {
+        // 4 of 5 miners can find a block during the 2s a block is in flight
+        let expected = 1.0 - (-0.8 * 2.0_f64 / 10.0).exp();
+        let measured = stale_rate(2.0, 3);
+        assert!((measured - expected).abs() < 0.05, "{} vs {}", measured, expected);
+    }
+}
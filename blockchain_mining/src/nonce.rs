@@ -0,0 +1,102 @@
+// How much of the header nonce a miner searches before rolling the extra
+// nonce. Real headers use all 64 bits; tests shrink it to force exhaustion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonceSpace {
+    bits: u32,
+}
+
+impl NonceSpace {
+    pub const FULL: NonceSpace = NonceSpace { bits: u64::BITS };
+
+    #[cfg(test)]
+    pub fn with_bits(bits: u32) -> Self {
+        assert!((1..=u64::BITS).contains(&bits), "nonce width must be 1-64 bits");
+        NonceSpace { bits }
+    }
+
+    pub fn max_nonce(self) -> u64 {
+        u64::MAX >> (u64::BITS - self.bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{MiningLimits, MiningOutcome, StopReason};
+    use crate::target::Target;
+    use crate::Block;
+    use std::collections::HashSet;
+
+    fn template(target: Target) -> Block {
+        Block::new(1, "nonce test".to_string(), "0".repeat(64), target)
+    }
+
+    #[test]
+    fn rolls_extra_nonce_when_nonce_runs_out() {
+        let space = NonceSpace::with_bits(3);
+        let mut block = template(Target::max());
+        for _ in 0..8 {
+            assert_eq!(block.extra_nonce, 0);
+            block.advance_nonce(space);
+        }
+        assert_eq!((block.nonce, block.extra_nonce), (0, 1));
+    }
+
+    #[test]
+    fn full_width_nonce_rolls_instead_of_overflowing() {
+        let mut block = template(Target::max());
+        block.nonce = u64::MAX;
+        block.advance_nonce(NonceSpace::FULL);
+        assert_eq!((block.nonce, block.extra_nonce), (0, 1));
+    }
+
+    #[test]
+    fn exhausted_nonce_space_never_repeats_a_header() {
+        let space = NonceSpace::with_bits(2);
+        let mut block = template(Target::max());
+        let mut positions = HashSet::new();
+        let mut hashes = HashSet::new();
+        for _ in 0..200 {
+            assert!(block.nonce <= space.max_nonce());
+            assert!(positions.insert((block.extra_nonce, block.nonce)));
+            assert!(hashes.insert(block.calculate_hash()));
+            block.advance_nonce(space);
+        }
+        assert_eq!(block.extra_nonce, 50);
+    }
+
+    #[test]
+    fn refreshed_timestamp_never_goes_backwards() {
+        let space = NonceSpace::with_bits(1);
+        let mut block = template(Target::max());
+        let mut previous = block.timestamp;
+        for _ in 0..10 {
+            block.advance_nonce(space);
+            assert!(block.timestamp >= previous);
+            previous = block.timestamp;
+        }
+    }
+
+    #[test]
+    fn mining_with_tiny_nonce_finds_valid_block() {
+        // About 256 attempts expected, far more than a 2-bit nonce holds
+        let mut block = template(Target::from_leading_zeros(2));
+        let outcome = block.mine_in(NonceSpace::with_bits(2), &MiningLimits::none(), &mut ());
+        let MiningOutcome::Found(stats) = outcome else {
+            panic!("mining without limits must find a block");
+        };
+        assert!(block.verify_pow().is_ok());
+        // Every attempt was a distinct step through the search space
+        assert_eq!(stats.attempts, block.extra_nonce * 4 + block.nonce + 1);
+    }
+
+    #[test]
+    fn budget_stops_at_the_expected_search_position() {
+        let mut block = template(Target::from_leading_zeros(16));
+        let limits = MiningLimits { max_attempts: Some(100), ..MiningLimits::none() };
+        let outcome = block.mine_in(NonceSpace::with_bits(3), &limits, &mut ());
+        assert!(matches!(outcome, MiningOutcome::NotFound { reason: StopReason::AttemptsExhausted, attempts: 100 }));
+        // Attempt 100 was extra nonce 12, nonce 3
+        assert_eq!((block.extra_nonce, block.nonce), (12, 3));
+    }
+}
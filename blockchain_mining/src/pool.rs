@@ -0,0 +1,265 @@
+use crate::header::HeaderHasher;
+use crate::rewards::{block_subsidy, format_coins, Coinbase};
+use crate::target::{to_hex, Target};
+use crate::{Block, MiningStats};
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct PoolMember {
+    pub name: String,
+    // Relative hash power: nonces tried per round
+    pub attempts_per_round: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayoutScheme {
+    // Pay-per-share: every share is paid its expected value right away, the
+    // pool operator carries the luck
+    Pps,
+    // Pay-per-last-N-shares: each block's reward is split over the last
+    // `window` shares, so members carry the luck
+    Pplns { window: usize },
+}
+
+impl PayoutScheme {
+    pub fn name(&self) -> String {
+        match self {
+            PayoutScheme::Pps => "PPS".to_string(),
+            PayoutScheme::Pplns { window } => format!("PPLNS (N={})", window),
+        }
+    }
+
+    // What each member earns from a finished run, after the pool fee
+    pub fn payouts(&self, log: &PoolLog, fee: f64) -> Vec<u64> {
+        let mut earned = vec![0u64; log.members];
+        match *self {
+            PayoutScheme::Pps => {
+                for share in &log.shares {
+                    let expected = share.block_reward as f64 * log.share_difficulty / log.block_difficulty;
+                    earned[share.member] += (expected * (1.0 - fee)) as u64;
+                }
+            }
+            PayoutScheme::Pplns { window } => {
+                for block in &log.blocks {
+                    let start = block.share_index.saturating_sub(window);
+                    let shares = &log.shares[start..block.share_index];
+                    let pot = block.reward as f64 * (1.0 - fee);
+                    for share in shares {
+                        earned[share.member] += (pot / shares.len() as f64) as u64;
+                    }
+                }
+            }
+        }
+        earned
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShareRecord {
+    pub member: usize,
+    // Reward of the block the pool was working on when the share was found
+    pub block_reward: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockRecord {
+    // Shares submitted before (and including) the one that found this block
+    pub share_index: usize,
+    pub reward: u64,
+    pub finder: usize,
+}
+
+// Everything a pool run produced, enough to replay any payout scheme
+#[derive(Debug, Clone)]
+pub struct PoolLog {
+    pub members: usize,
+    pub share_difficulty: f64,
+    pub block_difficulty: f64,
+    pub shares: Vec<ShareRecord>,
+    pub blocks: Vec<BlockRecord>,
+    pub member_stats: Vec<MiningStats>,
+}
+
+impl PoolLog {
+    pub fn total_rewards(&self) -> u64 {
+        self.blocks.iter().map(|block| block.reward).sum()
+    }
+}
+
+pub struct PoolConfig {
+    pub members: Vec<PoolMember>,
+    pub share_target: Target,
+    pub block_target: Target,
+    pub blocks: usize,
+    pub halving_interval: u64,
+}
+
+// The pool hashes on one block template; members take turns in rounds, each
+// searching its own slice of the nonce space (the member index goes in the
+// top bits) so no work is duplicated. Any hash meeting the easy share target
+// is a share, and a share that also meets the block target wins the block.
+pub fn run(genesis: &Block, config: &PoolConfig) -> PoolLog {
+    let mut rng = rand::thread_rng();
+    let mut log = PoolLog {
+        members: config.members.len(),
+        share_difficulty: config.share_target.difficulty(),
+        block_difficulty: config.block_target.difficulty(),
+        shares: Vec::new(),
+        blocks: Vec::new(),
+        member_stats: vec![MiningStats { attempts: 0, total_time: Duration::ZERO }; config.members.len()],
+    };
+
+    let mut tip = genesis.clone();
+    let mut next_nonce = vec![0u64; config.members.len()];
+
+    while log.blocks.len() < config.blocks {
+        let height = tip.index + 1;
+        let mut template = Block::new(height, "Pool block".to_string(), tip.hash.clone(), config.block_target);
+        template.coinbase = Coinbase {
+            miner: "Pool".to_string(),
+            subsidy: block_subsidy(height, config.halving_interval),
+            fees: 0,
+        };
+        let reward = template.coinbase.reward().expect("a subsidy without fees fits");
+        // Only the nonce changes from here on
+        let header = HeaderHasher::new(&template);
+
+        'round: loop {
+            for (member, settings) in config.members.iter().enumerate() {
+                let start = Instant::now();
+                // A little noise so equal members don't tie every round
+                let attempts = rng.gen_range(settings.attempts_per_round / 2..=settings.attempts_per_round * 3 / 2);
+                let stats = &mut log.member_stats[member];
+
+                for _ in 0..attempts {
+                    template.nonce = ((member as u64) << 48) | next_nonce[member];
+                    next_nonce[member] += 1;
+                    stats.attempts += 1;
+
+                    let hash_bytes = header.hash(template.nonce, template.extra_nonce);
+                    if !config.share_target.is_met_by(&hash_bytes) {
+                        continue;
+                    }
+                    log.shares.push(ShareRecord { member, block_reward: reward });
+
+                    if config.block_target.is_met_by(&hash_bytes) {
+                        template.hash = to_hex(&hash_bytes);
+                        log.blocks.push(BlockRecord { share_index: log.shares.len(), reward, finder: member });
+                        stats.total_time += start.elapsed();
+                        break 'round;
+                    }
+                }
+                stats.total_time += start.elapsed();
+            }
+        }
+
+        tip = template;
+        next_nonce.iter_mut().for_each(|nonce| *nonce = 0);
+    }
+
+    log
+}
+
+pub fn display_report(log: &PoolLog, config: &PoolConfig, fee: f64) {
+    // PPLNS over roughly two blocks' worth of shares
+    let window = (2.0 * log.block_difficulty / log.share_difficulty) as usize;
+    let schemes = [PayoutScheme::Pps, PayoutScheme::Pplns { window }];
+    let total_attempts: u64 = log.member_stats.iter().map(|s| s.attempts).sum();
+    let total_rewards = log.total_rewards();
+
+    println!("\n🏊 MINING POOL REPORT");
+    println!("───────────────────────────");
+    println!("⛏️  {} blocks found, {} shares, {} hashes", log.blocks.len(), log.shares.len(), total_attempts);
+    println!("💰 Block rewards: {} (pool fee {:.1}%)", format_coins(total_rewards), fee * 100.0);
+
+    let payouts: Vec<Vec<u64>> = schemes.iter().map(|scheme| scheme.payouts(log, fee)).collect();
+    for (member, settings) in config.members.iter().enumerate() {
+        let stats = &log.member_stats[member];
+        let hash_share = stats.attempts as f64 / total_attempts.max(1) as f64;
+        let shares = log.shares.iter().filter(|s| s.member == member).count();
+        let found = log.blocks.iter().filter(|b| b.finder == member).count();
+        let hps = stats.attempts as f64 / stats.total_time.as_secs_f64().max(f64::EPSILON);
+
+        println!("👷 {}: {:.1}% of hashes ({:.0} H/s), {} shares, {} blocks found",
+                 settings.name, hash_share * 100.0, hps, shares, found);
+        for (scheme, paid) in schemes.iter().zip(&payouts) {
+            let earned_share = paid[member] as f64 / total_rewards.max(1) as f64;
+            println!("      {}: earned {} ({:.1}% of rewards)", scheme.name(), format_coins(paid[member]), earned_share * 100.0);
+        }
+    }
+
+    for (scheme, paid) in schemes.iter().zip(&payouts) {
+        let paid_out: u64 = paid.iter().sum();
+        let operator = total_rewards as i128 - paid_out as i128;
+        let sign = if operator < 0 { "-" } else { "" };
+        println!("🏦 {} operator result: {}{}", scheme.name(), sign, format_coins(operator.unsigned_abs() as u64));
+    }
+    println!("───────────────────────────\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pow::PowAlgorithm;
+    use crate::rewards::COIN;
+
+    const REWARD: u64 = 50 * COIN;
+
+    // One block found by the last of `members`' shares, in that order
+    fn log(members: &[usize]) -> PoolLog {
+        PoolLog {
+            members: 2,
+            share_difficulty: 16.0,
+            block_difficulty: 256.0,
+            shares: members.iter().map(|&member| ShareRecord { member, block_reward: REWARD }).collect(),
+            blocks: vec![BlockRecord { share_index: members.len(), reward: REWARD, finder: members[members.len() - 1] }],
+            member_stats: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn pps_pays_each_share_its_expected_value_less_the_fee() {
+        let paid = PayoutScheme::Pps.payouts(&log(&[0, 0, 1]), 0.02);
+        // A share is a sixteenth of a block's work
+        let per_share = (REWARD as f64 / 16.0 * 0.98) as u64;
+        assert_eq!(paid, vec![2 * per_share, per_share]);
+    }
+
+    #[test]
+    fn pplns_only_pays_the_last_window_of_shares() {
+        let shares = [0, 0, 0, 0, 0, 0, 1, 1, 1, 1];
+        assert_eq!(PayoutScheme::Pplns { window: 4 }.payouts(&log(&shares), 0.0), vec![0, REWARD]);
+        assert_eq!(PayoutScheme::Pplns { window: 5 }.payouts(&log(&shares), 0.0), vec![REWARD / 5, REWARD * 4 / 5]);
+        // A window longer than the history pays everything there is
+        assert_eq!(PayoutScheme::Pplns { window: 100 }.payouts(&log(&shares), 0.0), vec![REWARD * 6 / 10, REWARD * 4 / 10]);
+        assert_eq!(PayoutScheme::Pplns { window: 4 }.payouts(&log(&shares), 0.1), vec![0, REWARD / 10 * 9]);
+    }
+
+    #[test]
+    fn pplns_payouts_add_up_to_the_block_rewards() {
+        let genesis = Block::genesis(PowAlgorithm::default(), &mut ());
+        let config = PoolConfig {
+            members: vec![
+                PoolMember { name: "Small".to_string(), attempts_per_round: 20 },
+                PoolMember { name: "Big".to_string(), attempts_per_round: 60 },
+            ],
+            share_target: Target::from_leading_zeros(1),
+            block_target: Target::from_leading_zeros(2),
+            blocks: 3,
+            halving_interval: 10,
+        };
+        let log = run(&genesis, &config);
+        assert_eq!(log.blocks.len(), 3);
+        assert_eq!(log.total_rewards(), 3 * REWARD);
+        assert!(log.blocks.windows(2).all(|pair| pair[0].share_index < pair[1].share_index));
+
+        for window in [1, 8, 1000] {
+            let paid: u64 = PayoutScheme::Pplns { window }.payouts(&log, 0.0).iter().sum();
+            // Each share's cut is rounded down, by less than a unit apiece
+            let shares_paid: usize = log.blocks.iter().map(|block| block.share_index.min(window)).sum();
+            assert!(paid <= log.total_rewards() && paid + shares_paid as u64 >= log.total_rewards(),
+                    "window {} paid {} of {}", window, paid, log.total_rewards());
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::header::HeaderHasher;
+use crate::target::Target;
+use crate::Block;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// Turns a header into the hash that is checked against the target. Every
+// algorithm starts from the SHA-256 digest of the serialized header, which
+// the midstate cache makes cheap, and does its own work on top of it.
+pub trait ProofOfWork {
+    fn finish(&self, header_digest: [u8; 32]) -> [u8; 32];
+
+    // Scratch memory one hash needs; what makes an algorithm costly for ASICs
+    fn memory_bytes(&self) -> usize;
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+// The original single SHA-256
+pub struct Sha256Single;
+
+impl ProofOfWork for Sha256Single {
+    fn finish(&self, header_digest: [u8; 32]) -> [u8; 32] {
+        header_digest
+    }
+
+    fn memory_bytes(&self) -> usize {
+        0
+    }
+}
+
+// Bitcoin's double SHA-256
+pub struct Sha256d;
+
+impl ProofOfWork for Sha256d {
+    fn finish(&self, header_digest: [u8; 32]) -> [u8; 32] {
+        sha256(&header_digest)
+    }
+
+    fn memory_bytes(&self) -> usize {
+        0
+    }
+}
+
+// scrypt's ROMix with SHA-256 as the mixing function: fill `cells` 32-byte
+// cells sequentially, then read them back in an order that depends on the
+// data. Skipping the table means recomputing cells, so memory is the cost.
+pub struct ScryptLike {
+    pub cells: usize,
+}
+
+impl ProofOfWork for ScryptLike {
+    fn finish(&self, header_digest: [u8; 32]) -> [u8; 32] {
+        let mut table = Vec::with_capacity(self.cells);
+        let mut x = header_digest;
+        for _ in 0..self.cells {
+            table.push(x);
+            x = sha256(&x);
+        }
+        for _ in 0..self.cells {
+            let j = u64::from_le_bytes(x[..8].try_into().unwrap()) as usize % self.cells;
+            for (byte, mixed) in x.iter_mut().zip(&table[j]) {
+                *byte ^= mixed;
+            }
+            x = sha256(&x);
+        }
+        x
+    }
+
+    fn memory_bytes(&self) -> usize {
+        self.cells * 32
+    }
+}
+
+// A small generalized birthday problem in the spirit of Equihash: expand the
+// digest into 2^list_bits values and find two whose top `collision_bits` bits
+// match, which in practice means sorting the whole list in memory. The
+// colliding pair is hashed into the proof. Unlike real Equihash the verifier
+// redoes the search instead of checking a submitted solution.
+pub struct EquihashLike {
+    pub list_bits: u32,
+    pub collision_bits: u32,
+}
+
+impl ProofOfWork for EquihashLike {
+    fn finish(&self, header_digest: [u8; 32]) -> [u8; 32] {
+        let len = 1u32 << self.list_bits;
+        let mut list: Vec<(u32, u32)> = Vec::with_capacity(len as usize);
+        // Each SHA-256 of (digest, counter) gives eight 32-bit values
+        for chunk in 0..len.div_ceil(8) {
+            let mut input = header_digest.to_vec();
+            input.extend_from_slice(&chunk.to_le_bytes());
+            let expanded = sha256(&input);
+            for (k, word) in expanded.chunks(4).enumerate() {
+                let index = chunk * 8 + k as u32;
+                if index < len {
+                    let value = u32::from_le_bytes(word.try_into().unwrap()) >> (32 - self.collision_bits);
+                    list.push((value, index));
+                }
+            }
+        }
+        list.sort_unstable();
+
+        match list.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            Some(pair) => {
+                let mut input = header_digest.to_vec();
+                input.extend_from_slice(&pair[0].1.to_le_bytes());
+                input.extend_from_slice(&pair[1].1.to_le_bytes());
+                sha256(&input)
+            }
+            // No solution for this nonce; a hash no target accepts
+            None => [0xff; 32],
+        }
+    }
+
+    fn memory_bytes(&self) -> usize {
+        (1usize << self.list_bits) * std::mem::size_of::<(u32, u32)>()
+    }
+}
+
+// Upper bounds on the memory-hard parameters a chain may ask for, so loading
+// someone else's chain can't make every hash allocate gigabytes
+const MAX_SCRYPT_CELLS: usize = 1 << 16;
+const MAX_LIST_BITS: u32 = 20;
+
+// Which proof of work a chain uses, stored with the chain and on each block
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum PowAlgorithm {
+    #[default]
+    Sha256,
+    Sha256d,
+    Scrypt { cells: usize },
+    Equihash { list_bits: u32, collision_bits: u32 },
+}
+
+impl PowAlgorithm {
+    // Sizes chosen so a demo chain still mines in seconds in a debug build
+    pub fn all() -> Vec<Self> {
+        vec![
+            PowAlgorithm::Sha256,
+            PowAlgorithm::Sha256d,
+            PowAlgorithm::Scrypt { cells: 1024 },
+            PowAlgorithm::Equihash { list_bits: 12, collision_bits: 20 },
+        ]
+    }
+
+    pub fn proof_of_work(&self) -> Box<dyn ProofOfWork> {
+        match *self {
+            PowAlgorithm::Sha256 => Box::new(Sha256Single),
+            PowAlgorithm::Sha256d => Box::new(Sha256d),
+            PowAlgorithm::Scrypt { cells } => Box::new(ScryptLike { cells }),
+            PowAlgorithm::Equihash { list_bits, collision_bits } => {
+                Box::new(EquihashLike { list_bits, collision_bits })
+            }
+        }
+    }
+
+    // Err with the reason if hashing with these parameters would divide by
+    // zero, overflow a shift or use more memory than we allow
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            PowAlgorithm::Scrypt { cells } if !(1..=MAX_SCRYPT_CELLS).contains(&cells) => {
+                Err(format!("scrypt cells must be between 1 and {}", MAX_SCRYPT_CELLS))
+            }
+            PowAlgorithm::Equihash { list_bits, .. } if !(1..=MAX_LIST_BITS).contains(&list_bits) => {
+                Err(format!("Equihash list_bits must be between 1 and {}", MAX_LIST_BITS))
+            }
+            PowAlgorithm::Equihash { collision_bits, .. } if !(1..=32).contains(&collision_bits) => {
+                Err("Equihash collision_bits must be between 1 and 32".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            PowAlgorithm::Sha256 => "SHA-256".to_string(),
+            PowAlgorithm::Sha256d => "SHA-256d".to_string(),
+            PowAlgorithm::Scrypt { cells } => format!("scrypt-like ({} cells)", cells),
+            PowAlgorithm::Equihash { list_bits, collision_bits } => {
+                format!("Equihash-like (2^{} list, {}-bit collision)", list_bits, collision_bits)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PowAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1024 {
+        format!("{} KiB", bytes / 1024)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+// Header hashes per second and memory per hash of every algorithm, each run
+// for `duration`
+pub fn display_comparison(duration: Duration) {
+    println!("\n🧪 PROOF-OF-WORK COMPARISON");
+    println!("───────────────────────────");
+    println!("{:<42}{:>14}{:>12}{:>12}", "algorithm", "H/s", "relative", "memory");
+
+    let mut baseline = None;
+    for algorithm in PowAlgorithm::all() {
+        let mut template = Block::new(1, "Benchmark block".to_string(), "0".repeat(64), Target::from_leading_zeros(16));
+        template.pow = algorithm;
+        let header = HeaderHasher::new(&template);
+        let start = Instant::now();
+        let mut hashes = 0u64;
+        while start.elapsed() < duration {
+            // Check the clock in batches so it doesn't dominate fast algorithms
+            for _ in 0..64 {
+                black_box(template.target.is_met_by(&header.hash(hashes, 0)));
+                hashes += 1;
+            }
+        }
+        let hps = hashes as f64 / start.elapsed().as_secs_f64();
+        let relative = hps / *baseline.get_or_insert(hps);
+        println!("{:<42}{:>14.0}{:>11.4}x{:>12}",
+                 algorithm.name(), hps, relative, format_bytes(algorithm.proof_of_work().memory_bytes()));
+    }
+
+    println!("───────────────────────────");
+    println!("SHA-256 needs no memory, so custom chips can pack thousands of cores.");
+    println!("Memory-hard algorithms make every parallel hash pay for its own RAM,");
+    println!("trading raw speed for resistance to specialised hardware.\n");
+}
@@ -0,0 +1,283 @@
+use crate::target::Target;
+use serde::{Deserialize, Serialize};
+
+// What a retarget algorithm gets to see about each block
+#[derive(Debug, Clone, Copy)]
+pub struct BlockTime {
+    pub timestamp: i64,
+    pub target: Target,
+}
+
+pub trait RetargetStrategy {
+    // Target for the block that will follow `history` (oldest first, never
+    // empty). `current` is the target in force right now, which differs from
+    // the last block's target only after a manual difficulty change.
+    fn next_target(&self, history: &[BlockTime], current: Target, target_time: u64) -> Target;
+}
+
+// The original rule: average the last 3 block times and only retarget when
+// they fall outside a 2x band around target_time
+pub struct LegacyBand;
+
+impl RetargetStrategy for LegacyBand {
+    fn next_target(&self, history: &[BlockTime], current: Target, target_time: u64) -> Target {
+        if history.len() < 2 {
+            return current;
+        }
+
+        let recent_blocks = std::cmp::min(3, history.len() - 1);
+        let first = &history[history.len() - 1 - recent_blocks];
+        let last = &history[history.len() - 1];
+        let avg_time = (last.timestamp - first.timestamp) as f64 / recent_blocks as f64;
+        let target_time = target_time as f64;
+
+        if avg_time < target_time / 2.0 || avg_time > target_time * 2.0 {
+            current.scale((avg_time / target_time).clamp(0.25, 4.0))
+        } else {
+            current
+        }
+    }
+}
+
+// Bitcoin-style: every `interval` blocks scale the target by how long the
+// window actually took versus how long it should have taken
+pub struct FixedWindow {
+    pub interval: u64,
+    pub max_factor: f64,
+}
+
+impl RetargetStrategy for FixedWindow {
+    fn next_target(&self, history: &[BlockTime], current: Target, target_time: u64) -> Target {
+        let interval = self.interval as usize;
+        let next_height = history.len();
+        if interval == 0 || !next_height.is_multiple_of(interval) || history.len() <= interval {
+            return current;
+        }
+
+        let first = &history[history.len() - 1 - interval];
+        let last = &history[history.len() - 1];
+        let actual = (last.timestamp - first.timestamp) as f64;
+        let expected = (interval as u64 * target_time) as f64;
+        let factor = (actual / expected).clamp(1.0 / self.max_factor, self.max_factor);
+
+        current.scale(factor)
+    }
+}
+
+// Linearly weighted moving average: recent solve times count the most, so it
+// reacts every block without overreacting to a single lucky one
+pub struct Lwma {
+    pub window: usize,
+}
+
+impl RetargetStrategy for Lwma {
+    fn next_target(&self, history: &[BlockTime], current: Target, target_time: u64) -> Target {
+        if history.len() < 3 {
+            return current;
+        }
+
+        let n = std::cmp::min(self.window, history.len() - 1);
+        let t = target_time as f64;
+        let mut weighted_times = 0.0;
+        let mut avg_target = 0.0;
+
+        for i in 1..=n {
+            let j = history.len() - 1 - n + i;
+            let solve_time = (history[j].timestamp - history[j - 1].timestamp) as f64;
+            weighted_times += i as f64 * solve_time.clamp(1.0, 6.0 * t);
+            avg_target += history[j].target.as_f64() / n as f64;
+        }
+
+        let k = (n * (n + 1) / 2) as f64;
+        Target::from_f64(avg_target * weighted_times / (k * t))
+    }
+}
+
+// Absolutely scheduled exponential retarget (as used by Bitcoin Cash): the
+// target doubles for every `half_life` seconds the chain is behind the ideal
+// schedule and halves for every `half_life` it is ahead. Anchored at genesis.
+pub struct Asert {
+    pub half_life: u64,
+}
+
+impl RetargetStrategy for Asert {
+    fn next_target(&self, history: &[BlockTime], _current: Target, target_time: u64) -> Target {
+        let anchor = &history[0];
+        let last = &history[history.len() - 1];
+        let time_delta = (last.timestamp - anchor.timestamp) as f64;
+        let height_delta = (history.len() - 1) as f64;
+        let exponent = (time_delta - target_time as f64 * height_delta) / self.half_life as f64;
+
+        anchor.target.scale(2f64.powf(exponent))
+    }
+}
+
+// Serializable selection of the retarget algorithm used by a chain
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum RetargetAlgorithm {
+    #[default]
+    Legacy,
+    FixedWindow { interval: u64, max_factor: f64 },
+    Lwma { window: usize },
+    Asert { half_life: u64 },
+}
+
+impl RetargetAlgorithm {
+    // Every algorithm with parameters that suit a short target_time demo chain
+    pub fn all() -> Vec<Self> {
+        vec![
+            RetargetAlgorithm::Legacy,
+            RetargetAlgorithm::FixedWindow { interval: 10, max_factor: 4.0 },
+            RetargetAlgorithm::Lwma { window: 20 },
+            RetargetAlgorithm::Asert { half_life: 60 },
+        ]
+    }
+
+    pub fn strategy(&self) -> Box<dyn RetargetStrategy> {
+        match *self {
+            RetargetAlgorithm::Legacy => Box::new(LegacyBand),
+            RetargetAlgorithm::FixedWindow { interval, max_factor } => {
+                Box::new(FixedWindow { interval, max_factor })
+            }
+            RetargetAlgorithm::Lwma { window } => Box::new(Lwma { window }),
+            RetargetAlgorithm::Asert { half_life } => Box::new(Asert { half_life }),
+        }
+    }
+
+    // LWMA and ASERT work from the blocks' own targets and never look at
+    // `current`, so a manual change re-anchors them instead: the block that
+    // records it takes the target as-is, and later blocks look back no further
+    pub fn reanchors_on_manual_target(&self) -> bool {
+        matches!(self, RetargetAlgorithm::Lwma { .. } | RetargetAlgorithm::Asert { .. })
+    }
+
+    // Err with the reason if the parameters can't work: a zero interval or
+    // half-life is divided by, and a window needs two blocks to time
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            RetargetAlgorithm::FixedWindow { interval: 0, .. } => {
+                Err("fixed window interval must be at least 1 block".to_string())
+            }
+            RetargetAlgorithm::FixedWindow { max_factor, .. } if !(max_factor.is_finite() && max_factor >= 1.0) => {
+                Err(format!("fixed window max_factor must be finite and at least 1, not {}", max_factor))
+            }
+            RetargetAlgorithm::Lwma { window } if window < 2 => {
+                Err("LWMA window must be at least 2 blocks".to_string())
+            }
+            RetargetAlgorithm::Asert { half_life: 0 } => {
+                Err("ASERT half_life must be at least 1 second".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            RetargetAlgorithm::Legacy => "Legacy (3-block band)".to_string(),
+            RetargetAlgorithm::FixedWindow { interval, max_factor } => {
+                format!("Fixed window ({} blocks, max {}x)", interval, max_factor)
+            }
+            RetargetAlgorithm::Lwma { window } => format!("LWMA ({} blocks)", window),
+            RetargetAlgorithm::Asert { half_life } => format!("ASERT (half-life {}s)", half_life),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_TIME: u64 = 10;
+
+    // `blocks` blocks `spacing` seconds apart, all at `target`
+    fn history(blocks: usize, spacing: i64, target: Target) -> Vec<BlockTime> {
+        (0..blocks as i64).map(|i| BlockTime { timestamp: i * spacing, target }).collect()
+    }
+
+    fn ratio(next: Target, current: Target) -> f64 {
+        next.as_f64() / current.as_f64()
+    }
+
+    fn assert_ratio(next: Target, current: Target, expected: f64) {
+        let actual = ratio(next, current);
+        assert!((actual / expected - 1.0).abs() < 1e-3, "expected {}x, got {}x", expected, actual);
+    }
+
+    #[test]
+    fn blocks_on_schedule_keep_the_target() {
+        let target = Target::from_leading_zeros(4);
+        // 20 blocks so the fixed window is due to retarget too
+        let on_time = history(20, TARGET_TIME as i64, target);
+        for algorithm in RetargetAlgorithm::all() {
+            let next = algorithm.strategy().next_target(&on_time, target, TARGET_TIME);
+            assert_ratio(next, target, 1.0);
+        }
+    }
+
+    #[test]
+    fn legacy_band_clamps_to_four_times() {
+        let target = Target::from_leading_zeros(4);
+        let fast = LegacyBand.next_target(&history(5, 0, target), target, TARGET_TIME);
+        assert_ratio(fast, target, 0.25);
+        let slow = LegacyBand.next_target(&history(5, 1000, target), target, TARGET_TIME);
+        assert_ratio(slow, target, 4.0);
+        // Inside the 2x band nothing changes
+        let close = LegacyBand.next_target(&history(5, 15, target), target, TARGET_TIME);
+        assert_eq!(close, target);
+    }
+
+    #[test]
+    fn fixed_window_clamps_to_max_factor() {
+        let target = Target::from_leading_zeros(4);
+        let window = FixedWindow { interval: 10, max_factor: 4.0 };
+        assert_ratio(window.next_target(&history(20, 1000, target), target, TARGET_TIME), target, 4.0);
+        assert_ratio(window.next_target(&history(20, 0, target), target, TARGET_TIME), target, 0.25);
+        // Within the clamp it scales by actual over expected
+        assert_ratio(window.next_target(&history(20, 20, target), target, TARGET_TIME), target, 2.0);
+    }
+
+    #[test]
+    fn fixed_window_only_retargets_on_the_boundary() {
+        let target = Target::from_leading_zeros(4);
+        let window = FixedWindow { interval: 10, max_factor: 4.0 };
+        // The next height is history.len(); the first window closes at 20
+        for blocks in [9, 10, 11, 19, 21] {
+            assert_eq!(window.next_target(&history(blocks, 20, target), target, TARGET_TIME), target, "{} blocks", blocks);
+        }
+        assert_ne!(window.next_target(&history(20, 20, target), target, TARGET_TIME), target);
+        assert_ne!(window.next_target(&history(30, 20, target), target, TARGET_TIME), target);
+    }
+
+    #[test]
+    fn lwma_clamps_each_solve_time() {
+        let target = Target::from_leading_zeros(4);
+        let lwma = Lwma { window: 20 };
+        // Solve times are held to 6x target_time at most and 1 second at least
+        assert_ratio(lwma.next_target(&history(30, 1000, target), target, TARGET_TIME), target, 6.0);
+        assert_ratio(lwma.next_target(&history(30, 0, target), target, TARGET_TIME), target, 0.1);
+        // Too short a history leaves `current` alone
+        let manual = Target::from_leading_zeros(2);
+        assert_eq!(lwma.next_target(&history(2, 0, target), manual, TARGET_TIME), manual);
+    }
+
+    #[test]
+    fn asert_doubles_per_half_life_behind_and_halves_per_half_life_ahead() {
+        let target = Target::from_leading_zeros(4);
+        let asert = Asert { half_life: 60 };
+        let mut chain = history(11, TARGET_TIME as i64, target);
+        assert_ratio(asert.next_target(&chain, target, TARGET_TIME), target, 1.0);
+
+        chain.last_mut().unwrap().timestamp += 60;
+        assert_ratio(asert.next_target(&chain, target, TARGET_TIME), target, 2.0);
+        chain.last_mut().unwrap().timestamp += 60;
+        assert_ratio(asert.next_target(&chain, target, TARGET_TIME), target, 4.0);
+        chain.last_mut().unwrap().timestamp -= 240;
+        assert_ratio(asert.next_target(&chain, target, TARGET_TIME), target, 0.25);
+    }
+
+    #[test]
+    fn only_lwma_and_asert_reanchor() {
+        let reanchoring: Vec<bool> = RetargetAlgorithm::all().iter().map(|a| a.reanchors_on_manual_target()).collect();
+        assert_eq!(reanchoring, vec![false, false, true, true]);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::retarget::{BlockTime, RetargetAlgorithm};
+use crate::target::Target;
+use rand::Rng;
+
+// The network hash rate is multiplied by `multiplier` from block `at_block` on
+#[derive(Debug, Clone, Copy)]
+pub struct HashRateShock {
+    pub at_block: usize,
+    pub multiplier: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetargetSimConfig {
+    pub blocks: usize,
+    pub target_time: u64,
+    pub base_hash_rate: f64,
+    pub shocks: Vec<HashRateShock>,
+}
+
+impl RetargetSimConfig {
+    // Hash rate jumps 10x, then crashes to a fifth of the original
+    pub fn with_default_shocks(target_time: u64, base_hash_rate: f64) -> Self {
+        RetargetSimConfig {
+            blocks: 300,
+            target_time,
+            base_hash_rate,
+            shocks: vec![
+                HashRateShock { at_block: 100, multiplier: 10.0 },
+                HashRateShock { at_block: 200, multiplier: 0.2 },
+            ],
+        }
+    }
+
+    pub fn hash_rate_at(&self, height: usize) -> f64 {
+        // Multipliers are relative to the base rate; the latest shock wins
+        self.shocks
+            .iter()
+            .filter(|shock| shock.at_block <= height)
+            .max_by_key(|shock| shock.at_block)
+            .map_or(self.base_hash_rate, |shock| self.base_hash_rate * shock.multiplier)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PhaseReport {
+    pub start_block: usize,
+    pub hash_rate: f64,
+    pub avg_block_time: f64,
+    // Average over the second half of the phase, once the retarget had time
+    pub settled_block_time: f64,
+    // Blocks until a 20-block rolling average first lands within 25% of target_time
+    pub blocks_to_converge: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetargetSimResult {
+    pub algorithm: RetargetAlgorithm,
+    pub block_times: Vec<f64>,
+    pub phases: Vec<PhaseReport>,
+    // The simulated chain as the retarget saw it, genesis first
+    pub history: Vec<BlockTime>,
+}
+
+// Mine `config.blocks` blocks without hashing: each solve time is drawn from
+// the exponential distribution with mean difficulty / hash_rate
+pub fn simulate(algorithm: RetargetAlgorithm, config: &RetargetSimConfig) -> RetargetSimResult {
+    simulate_with(algorithm, config, &mut rand::thread_rng())
+}
+
+// Same, drawing solve times from `rng` so a seeded run can be repeated
+pub fn simulate_with(algorithm: RetargetAlgorithm, config: &RetargetSimConfig, rng: &mut impl Rng) -> RetargetSimResult {
+    let strategy = algorithm.strategy();
+
+    // Start perfectly tuned for the base hash rate
+    let mut target = Target::from_difficulty(config.base_hash_rate * config.target_time as f64);
+    let mut clock = 0.0f64;
+    let mut history = vec![BlockTime { timestamp: 0, target }];
+    let mut block_times = Vec::with_capacity(config.blocks);
+
+    for height in 1..=config.blocks {
+        target = strategy.next_target(&history, target, config.target_time);
+
+        let mean = target.difficulty() / config.hash_rate_at(height);
+        let solve_time = -mean * (1.0 - rng.gen::<f64>()).ln();
+        clock += solve_time;
+
+        block_times.push(solve_time);
+        // Chain timestamps have whole-second resolution
+        history.push(BlockTime { timestamp: clock as i64, target });
+    }
+
+    let phases = phase_reports(config, &block_times);
+    RetargetSimResult { algorithm, block_times, phases, history }
+}
+
+fn phase_reports(config: &RetargetSimConfig, block_times: &[f64]) -> Vec<PhaseReport> {
+    let mut starts = vec![1];
+    starts.extend(config.shocks.iter().map(|shock| shock.at_block));
+    let target_time = config.target_time as f64;
+
+    starts
+        .iter()
+        .enumerate()
+        .filter(|(_, start)| **start <= block_times.len())
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(block_times.len() + 1);
+            let end = end.min(block_times.len() + 1);
+            // block_times[0] is the time of block 1
+            let times = &block_times[start - 1..end - 1];
+            let avg_block_time = average(times);
+            let settled_block_time = average(&times[times.len() / 2..]);
+
+            let window = 20;
+            let blocks_to_converge = (window..=times.len()).find(|&n| {
+                (average(&times[n - window..n]) - target_time).abs() <= target_time * 0.25
+            });
+
+            PhaseReport {
+                start_block: start,
+                hash_rate: config.hash_rate_at(start),
+                avg_block_time,
+                settled_block_time,
+                blocks_to_converge,
+            }
+        })
+        .collect()
+}
+
+fn average(times: &[f64]) -> f64 {
+    times.iter().sum::<f64>() / times.len().max(1) as f64
+}
+
+pub fn display_comparison(config: &RetargetSimConfig) {
+    println!("\n🧪 RETARGET SIMULATION");
+    println!("───────────────────────────");
+    println!("🎯 Target time: {}s, {} blocks", config.target_time, config.blocks);
+    for shock in &config.shocks {
+        println!("⚡ Block {}: hash rate x{}", shock.at_block, shock.multiplier);
+    }
+
+    for algorithm in RetargetAlgorithm::all() {
+        let result = simulate(algorithm, config);
+        let overall = average(&result.block_times);
+
+        println!("\n📐 {} — overall average {:.2}s", result.algorithm.name(), overall);
+        for phase in &result.phases {
+            let converge = match phase.blocks_to_converge {
+                Some(n) => format!("converged after {} blocks", n),
+                None => "never converged".to_string(),
+            };
+            println!("   from block {:>4} @ {:>10.0} H/s: avg {:>7.2}s, settled {:>7.2}s, {}",
+                     phase.start_block, phase.hash_rate, phase.avg_block_time,
+                     phase.settled_block_time, converge);
+        }
+    }
+    println!("───────────────────────────\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn hash_rate_follows_the_latest_shock() {
+        let config = RetargetSimConfig::with_default_shocks(10, 1000.0);
+        assert_eq!(config.hash_rate_at(99), 1000.0);
+        assert_eq!(config.hash_rate_at(100), 10_000.0);
+        assert_eq!(config.hash_rate_at(250), 200.0);
+    }
+
+    // Only up to the crash: one slow block after it can leave a 60s ASERT
+    // dozens of half-lives behind, pinned at the easiest target for a long time
+    #[test]
+    fn every_algorithm_but_legacy_settles_near_target_time_after_a_jump() {
+        let config = RetargetSimConfig::with_default_shocks(10, 1000.0);
+        for algorithm in RetargetAlgorithm::all() {
+            let result = simulate_with(algorithm, &config, &mut StdRng::seed_from_u64(7));
+            assert_eq!(result.block_times.len(), config.blocks);
+            assert_eq!(result.history.len(), config.blocks + 1);
+            assert_eq!(result.phases.iter().map(|phase| phase.start_block).collect::<Vec<_>>(), vec![1, 100, 200]);
+            if algorithm == RetargetAlgorithm::Legacy {
+                continue;
+            }
+            for phase in &result.phases[..2] {
+                assert!((phase.settled_block_time - 10.0).abs() < 5.0, "{} settled at {:.2}s from block {}",
+                        algorithm.name(), phase.settled_block_time, phase.start_block);
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_runs_repeat() {
+        let config = RetargetSimConfig::with_default_shocks(10, 1000.0);
+        let algorithm = RetargetAlgorithm::Lwma { window: 20 };
+        let first = simulate_with(algorithm, &config, &mut StdRng::seed_from_u64(3));
+        let second = simulate_with(algorithm, &config, &mut StdRng::seed_from_u64(3));
+        assert_eq!(first.block_times, second.block_times);
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+// Amounts are in the smallest unit, like satoshis
+pub const COIN: u64 = 100_000_000;
+pub const INITIAL_SUBSIDY: u64 = 50 * COIN;
+pub const DEFAULT_HALVING_INTERVAL: u64 = 10;
+// Fees are credited to the miner without anyone on the chain paying them, so
+// a block can't offer more than one initial subsidy's worth
+pub const MAX_FEE: u64 = INITIAL_SUBSIDY;
+
+// Pays the miner of a block: newly minted subsidy plus the fees offered for
+// the block's data. Part of the block hash, so it can't be changed afterwards.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Coinbase {
+    pub miner: String,
+    pub subsidy: u64,
+    pub fees: u64,
+}
+
+impl Coinbase {
+    // None if the two don't fit in a u64, which validation rules out
+    pub fn reward(&self) -> Option<u64> {
+        self.subsidy.checked_add(self.fees)
+    }
+}
+
+// Subsidy for the block at `height`, halving every `halving_interval` blocks.
+// Genesis mints nothing.
+pub fn block_subsidy(height: u64, halving_interval: u64) -> u64 {
+    if height == 0 {
+        return 0;
+    }
+    let halvings = (height - 1) / halving_interval.max(1);
+    if halvings >= 64 {
+        0
+    } else {
+        INITIAL_SUBSIDY >> halvings
+    }
+}
+
+pub fn format_coins(amount: u64) -> String {
+    format!("{}.{:08}", amount / COIN, amount % COIN)
+}
+
+// A fee in coins; anything above MAX_FEE is refused rather than saturated
+pub fn parse_coins(text: &str) -> Option<u64> {
+    let amount: f64 = text.trim().parse().ok()?;
+    if amount.is_finite() && amount >= 0.0 && amount * COIN as f64 <= MAX_FEE as f64 {
+        Some((amount * COIN as f64).round() as u64)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fees_up_to_the_cap() {
+        assert_eq!(parse_coins("1.5"), Some(150_000_000));
+        assert_eq!(parse_coins(" 0 "), Some(0));
+        assert_eq!(parse_coins("50"), Some(MAX_FEE));
+        assert_eq!(parse_coins("50.00000001"), None);
+        assert_eq!(parse_coins("2e11"), None);
+        assert_eq!(parse_coins("-1"), None);
+        assert_eq!(parse_coins("inf"), None);
+        assert_eq!(parse_coins("NaN"), None);
+    }
+
+    #[test]
+    fn reward_overflow_is_none_instead_of_wrapping() {
+        let coinbase = Coinbase { miner: "m".to_string(), subsidy: INITIAL_SUBSIDY, fees: u64::MAX };
+        assert_eq!(coinbase.reward(), None);
+        let coinbase = Coinbase { fees: MAX_FEE, ..coinbase };
+        assert_eq!(coinbase.reward(), Some(INITIAL_SUBSIDY + MAX_FEE));
+    }
+}
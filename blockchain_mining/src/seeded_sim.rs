@@ -0,0 +1,206 @@
+use crate::block_tree::BlockTree;
+use crate::header::HeaderHasher;
+use crate::retarget::RetargetAlgorithm;
+use crate::rewards::format_coins;
+use crate::target::to_hex;
+use crate::timestamps;
+use crate::{Block, Blockchain, MiningMethod, MiningStats};
+use chrono::DateTime;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+// Virtual time starts here so chains don't depend on when they were simulated
+const START_TIME: i64 = 1_600_000_000;
+
+pub struct SeededSimConfig {
+    pub seed: u64,
+    // Hashes per virtual second of each miner
+    pub hash_rates: Vec<f64>,
+    pub blocks: usize,
+    pub target_time: u64,
+    pub retarget: RetargetAlgorithm,
+    pub halving_interval: u64,
+}
+
+pub struct SeededSimReport {
+    pub blockchain: Blockchain,
+    pub blocks_won: Vec<usize>,
+    pub virtual_seconds: f64,
+    // SHA-256 of the serialized chain; equal digests mean identical runs
+    pub digest: String,
+}
+
+// Seconds on the virtual clock. Every hash attempt of miner `i` takes exactly
+// 1 / hash_rates[i] of them.
+struct VirtualClock {
+    now: f64,
+}
+
+impl VirtualClock {
+    fn timestamp(&self) -> i64 {
+        START_TIME + self.now as i64
+    }
+}
+
+// Real hashing on real blocks, but with time taken from a virtual clock and
+// all randomness from a seeded RNG, so the same config always produces the
+// same chain byte for byte.
+pub fn run(config: &SeededSimConfig) -> SeededSimReport {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut clock = VirtualClock { now: 0.0 };
+
+    let mut genesis = Block::new(0, "🌟 Genesis Block - The adventure begins!".to_string(),
+                                 "0".to_string(), Block::genesis_target());
+    genesis.timestamp = DateTime::from_timestamp(clock.timestamp(), 0).expect("timestamp in range");
+    let header = HeaderHasher::new(&genesis);
+    while !genesis.target.is_met_by(&header.hash(genesis.nonce, genesis.extra_nonce)) {
+        genesis.nonce += 1;
+    }
+    genesis.hash = to_hex(&header.hash(genesis.nonce, genesis.extra_nonce));
+
+    let mut blockchain = Blockchain {
+        tree: BlockTree::new(genesis.clone()),
+        chain: vec![genesis],
+        target: Block::genesis_target(),
+        target_time: config.target_time,
+        retarget: config.retarget,
+        mining_stats: Vec::new(),
+        stats_by_hash: BTreeMap::new(),
+        manual_difficulty_changes: Vec::new(),
+        halving_interval: config.halving_interval,
+        pow: Default::default(),
+    };
+    let mut blocks_won = vec![0; config.hash_rates.len()];
+
+    for _ in 0..config.blocks {
+        let history = blockchain.block_times();
+        blockchain.target = config.retarget.strategy().next_target(&history, blockchain.target, config.target_time);
+
+        let height = blockchain.chain.len() as u64;
+        let timestamps: Vec<i64> = history.iter().map(|block| block.timestamp).collect();
+        let timestamp = clock.timestamp().max(timestamps::earliest_allowed(&timestamps));
+        let previous_hash = blockchain.last_block().hash.clone();
+
+        let mut templates: Vec<Block> = (0..config.hash_rates.len())
+            .map(|miner| {
+                let mut block = Block::new(height, format!("Simulated block {}", height), previous_hash.clone(), blockchain.target);
+                block.timestamp = DateTime::from_timestamp(timestamp, 0).expect("timestamp in range");
+                block.coinbase = blockchain.coinbase_for(height, &format!("Miner {}", miner + 1), 0);
+                // The chain's algorithm replaces the default from the first block
+                block.retarget = (height == 1).then_some(config.retarget);
+                // Leave plenty of room before the nonce would roll over
+                block.nonce = rng.gen::<u32>() as u64;
+                block
+            })
+            .collect();
+        let headers: Vec<HeaderHasher> = templates.iter().map(HeaderHasher::new).collect();
+
+        // Each miner's next attempt is due at a fixed interval on the virtual
+        // clock; whoever is due first hashes next
+        let block_start = clock.now;
+        let mut attempts = vec![0u64; templates.len()];
+        let winner = loop {
+            let miner = (0..templates.len())
+                .min_by(|&a, &b| {
+                    let due_a = (attempts[a] + 1) as f64 / config.hash_rates[a];
+                    let due_b = (attempts[b] + 1) as f64 / config.hash_rates[b];
+                    due_a.total_cmp(&due_b)
+                })
+                .expect("at least one miner");
+            attempts[miner] += 1;
+            clock.now = block_start + attempts[miner] as f64 / config.hash_rates[miner];
+
+            let block = &mut templates[miner];
+            let hash_bytes = headers[miner].hash(block.nonce, block.extra_nonce);
+            if block.target.is_met_by(&hash_bytes) {
+                block.hash = to_hex(&hash_bytes);
+                break miner;
+            }
+            block.nonce += 1;
+        };
+
+        blocks_won[winner] += 1;
+        let stats = MiningStats {
+            attempts: attempts.iter().sum(),
+            total_time: Duration::from_secs_f64(clock.now - block_start),
+        };
+        let block = templates.swap_remove(winner);
+        blockchain.record_stats(&block.hash, stats, MiningMethod::Competition);
+        blockchain.receive_block(block).expect("simulated blocks follow the rules");
+    }
+
+    let serialized = serde_json::to_vec(&blockchain).expect("chain serializes");
+    SeededSimReport {
+        blockchain,
+        blocks_won,
+        virtual_seconds: clock.now,
+        digest: to_hex(&Sha256::digest(&serialized)),
+    }
+}
+
+pub fn display_report(config: &SeededSimConfig) {
+    println!("\n🎲 SEEDED SIMULATION: seed {}, {} blocks, {}", config.seed, config.blocks, config.retarget.name());
+    println!("───────────────────────────");
+    let start = Instant::now();
+    let report = run(config);
+    let wall_time = start.elapsed();
+
+    let total_rate: f64 = config.hash_rates.iter().sum();
+    for (miner, rate) in config.hash_rates.iter().enumerate() {
+        let won = report.blocks_won[miner];
+        println!("⛏️  Miner {} ({:.0} H/s): {} blocks ({:.1}%, expected {:.1}%)",
+                 miner + 1, rate, won, 100.0 * won as f64 / config.blocks.max(1) as f64, 100.0 * rate / total_rate);
+    }
+    let chain = &report.blockchain;
+    println!("⏱️  Virtual time: {:.0}s, {:.2}s per block (target {}s)",
+             report.virtual_seconds, report.virtual_seconds / config.blocks.max(1) as f64, config.target_time);
+    println!("🔶 Final difficulty: {:.0}", chain.target.difficulty());
+    println!("💰 Coin supply: {}", chain.total_supply().map_or("overflow".to_string(), format_coins));
+    match chain.validate() {
+        Ok(()) => println!("✅ Simulated chain is valid"),
+        Err(e) => println!("❌ Simulated chain is invalid: {}", e),
+    }
+    println!("🔑 Chain digest: {}", report.digest);
+    println!("🖥️  Simulated in {:.2}s of real time", wall_time.as_secs_f64());
+
+    let again = run(config);
+    if again.digest == report.digest {
+        println!("🔁 Re-ran with the same seed: identical chain");
+    } else {
+        println!("❌ Re-ran with the same seed and got a different chain: {}", again.digest);
+    }
+    println!("───────────────────────────\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(seed: u64) -> SeededSimConfig {
+        SeededSimConfig {
+            seed,
+            hash_rates: vec![5.0, 15.0],
+            blocks: 30,
+            target_time: 10,
+            retarget: RetargetAlgorithm::Lwma { window: 20 },
+            halving_interval: 100,
+        }
+    }
+
+    #[test]
+    fn same_seed_same_chain() {
+        let first = run(&config(7));
+        let second = run(&config(7));
+        assert_eq!(first.digest, second.digest);
+        assert_eq!(first.blocks_won, second.blocks_won);
+        first.blockchain.validate().unwrap();
+    }
+
+    #[test]
+    fn different_seeds_different_chains() {
+        assert_ne!(run(&config(7)).digest, run(&config(8)).digest);
+    }
+}
@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// 256-bit proof-of-work target stored in Bitcoin's compact "bits" encoding:
+// the high byte is the size of the number in bytes and the low 3 bytes are
+// its most significant digits. A hash is valid when hash <= target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Target(u32);
+
+impl Target {
+    // Easiest target we allow (every byte 0xff, rounded to compact precision)
+    pub fn max() -> Self {
+        Self::from_bytes(&[0xff; 32])
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        // Normalize so two encodings of the same number compare equal
+        Self::from_bytes(&Self(bits).to_bytes())
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    // Target equivalent to the old "N leading hex zeros" rule: 2^(256 - 4N) - 1
+    pub fn from_leading_zeros(zeros: u64) -> Self {
+        let mut bytes = [0xffu8; 32];
+        let zeros = zeros.min(64) as usize;
+        for byte in bytes.iter_mut().take(zeros / 2) {
+            *byte = 0;
+        }
+        if zeros % 2 == 1 {
+            bytes[zeros / 2] = 0x0f;
+        }
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let first = match bytes.iter().position(|b| *b != 0) {
+            Some(i) => i,
+            None => return Self(0),
+        };
+
+        let mut size = (32 - first) as u32;
+        let mut mantissa = 0u32;
+        for k in 0..3 {
+            mantissa <<= 8;
+            if let Some(b) = bytes.get(first + k) {
+                mantissa |= *b as u32;
+            }
+        }
+
+        // The top mantissa bit is a sign bit in the compact format
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            size += 1;
+        }
+
+        Self((size << 24) | mantissa)
+    }
+
+    pub fn to_bytes(self) -> [u8; 32] {
+        let size = (self.0 >> 24) as i64;
+        let mantissa = self.0 & 0x007f_ffff;
+        let mut bytes = [0u8; 32];
+
+        for k in 0..3i64 {
+            let b = ((mantissa >> (8 * (2 - k))) & 0xff) as u8;
+            let pos = 32 - size + k;
+            if pos < 0 {
+                if b != 0 {
+                    // Doesn't fit in 256 bits
+                    return [0xff; 32];
+                }
+            } else if pos < 32 {
+                bytes[pos as usize] = b;
+            }
+        }
+        bytes
+    }
+
+    // Approximate numeric value, good enough for retarget arithmetic since the
+    // compact encoding only keeps 24 bits of precision anyway
+    pub fn as_f64(self) -> f64 {
+        self.to_bytes()
+            .iter()
+            .fold(0.0, |acc, b| acc * 256.0 + *b as f64)
+    }
+
+    // Numeric value rounded to compact precision and clamped to [1, max]
+    pub fn from_f64(value: f64) -> Self {
+        if value.is_nan() || value < 1.0 {
+            return Self::from_bits(0x0101_0000);
+        }
+
+        let mut bytes = [0u8; 32];
+        let mut rest = value;
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let scale = 256f64.powi(31 - i as i32);
+            let digit = (rest / scale).floor().min(255.0);
+            *byte = digit as u8;
+            rest -= digit * scale;
+        }
+
+        if value >= 256f64.powi(32) {
+            return Self::max();
+        }
+        Self::from_bytes(&bytes)
+    }
+
+    // Scale the target by a factor: >1 makes mining easier, <1 harder
+    pub fn scale(self, factor: f64) -> Self {
+        Self::from_f64(self.as_f64() * factor)
+    }
+
+    // Target at which `difficulty` hashes are expected per block
+    pub fn from_difficulty(difficulty: f64) -> Self {
+        Self::from_f64(256f64.powi(32) / difficulty.max(1.0))
+    }
+
+    pub fn is_met_by(self, hash: &[u8; 32]) -> bool {
+        // Big-endian byte arrays compare lexicographically like numbers
+        *hash <= self.to_bytes()
+    }
+
+    // Expected number of hashes to find a block: 2^256 / (target + 1)
+    pub fn difficulty(self) -> f64 {
+        256f64.powi(32) / (self.as_f64() + 1.0)
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:08x}", self.0)
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_round_trip_through_bytes() {
+        for bits in [0x1d00_ffff, 0x1f00_ffff, 0x1f0f_ffff, 0x2000_ffff, 0x0312_3456, 0x0112_0000] {
+            let target = Target::from_bits(bits);
+            assert_eq!(target.bits(), bits);
+            assert_eq!(Target::from_bytes(&target.to_bytes()), target);
+        }
+    }
+
+    #[test]
+    fn non_canonical_bits_normalize() {
+        // The same number written with a leading zero byte in the mantissa
+        assert_eq!(Target::from_bits(0x0400_1234), Target::from_bits(0x0312_3400));
+        assert_eq!(Target::from_bytes(&[0; 32]).bits(), 0);
+        // Sizes below 3 drop the mantissa bytes past the end, as in Bitcoin
+        assert_eq!(Target::from_bits(0x0100_1200).bits(), 0);
+    }
+
+    #[test]
+    fn high_mantissa_bit_moves_into_the_size() {
+        let mut bytes = [0u8; 32];
+        bytes[29] = 0x80;
+        bytes[30] = 0x12;
+        bytes[31] = 0x34;
+        let target = Target::from_bytes(&bytes);
+        // 0x801234 would read as negative, so it's stored as 0x008012 with one
+        // more byte, losing the last one to compact precision
+        assert_eq!(target.bits(), 0x0400_8012);
+        let mut expected = [0u8; 32];
+        expected[29] = 0x80;
+        expected[30] = 0x12;
+        assert_eq!(target.to_bytes(), expected);
+    }
+
+    #[test]
+    fn oversized_targets_saturate() {
+        assert_eq!(Target::max().bits(), 0x2100_ffff);
+        assert_eq!(Target(0x2212_3456).to_bytes(), [0xff; 32]);
+        assert_eq!(Target::from_bits(0x2212_3456), Target::max());
+        // A zero leading mantissa byte still fits one size above 32
+        let bytes = Target::from_bits(0x2100_1234).to_bytes();
+        assert_eq!((bytes[0], bytes[1], bytes[2]), (0x12, 0x34, 0x00));
+    }
+
+    #[test]
+    fn leading_zeros_match_the_old_hex_rule() {
+        // A hash that is all zeros except for hex digit `position`
+        let hash_with_digit = |position: u64| {
+            let mut hash = [0u8; 32];
+            let shift = if position.is_multiple_of(2) { 4 } else { 0 };
+            hash[(position / 2) as usize] = 1 << shift;
+            hash
+        };
+        for zeros in 1..=9u64 {
+            let target = Target::from_leading_zeros(zeros);
+            assert!(!target.is_met_by(&hash_with_digit(zeros - 1)), "{} zeros: one too few passes", zeros);
+            assert!(target.is_met_by(&hash_with_digit(zeros)), "{} zeros: exactly enough fails", zeros);
+        }
+        assert_eq!(Target::from_leading_zeros(3).bits(), 0x1f0f_ffff);
+        assert_eq!(Target::from_leading_zeros(4).bits(), 0x1f00_ffff);
+        assert_eq!(Target::from_leading_zeros(64).bits(), 0);
+    }
+
+    #[test]
+    fn from_f64_rounds_down_to_compact_precision() {
+        // Below 2^24 only the fraction is lost
+        assert_eq!(Target::from_f64(255.9).as_f64(), 255.0);
+        for value in [65_537.0, 1.234_567e20, 3.0e60, 1.0e76] {
+            let target = Target::from_f64(value);
+            let rounded = target.as_f64();
+            assert!(rounded <= value, "{} rounded up to {}", value, rounded);
+            // At least 15 significant bits survive
+            assert!((value - rounded) / value < 1.0 / 32_768.0, "{} became {}", value, rounded);
+            assert_eq!(Target::from_f64(rounded), target);
+        }
+    }
+
+    #[test]
+    fn from_f64_clamps() {
+        let smallest = Target::from_bits(0x0101_0000);
+        assert_eq!(Target::from_f64(0.5), smallest);
+        assert_eq!(Target::from_f64(-3.0), smallest);
+        assert_eq!(Target::from_f64(f64::NAN), smallest);
+        assert_eq!(Target::from_f64(1e90), Target::max());
+        assert_eq!(Target::from_f64(f64::INFINITY), Target::max());
+    }
+
+    #[test]
+    fn difficulty_round_trips() {
+        for difficulty in [1.0, 256.0, 12_345.0, 1e9] {
+            let target = Target::from_difficulty(difficulty);
+            assert!((target.difficulty() - difficulty).abs() / difficulty < 1e-4);
+        }
+        // Scaling by 2 halves the work
+        let target = Target::from_difficulty(1e6);
+        assert!((target.scale(2.0).difficulty() - 5e5).abs() / 5e5 < 1e-4);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::retarget::{BlockTime, RetargetAlgorithm};
+use crate::target::Target;
+use crate::timestamps;
+
+// How long the honest chain runs before the attacker takes over; enough
+// blocks for a full median-time-past window
+const HONEST_BLOCKS: usize = 20;
+// Without rules the attacker could stamp anything; a day ahead is plenty
+const UNRULED_LEAD: i64 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimestampRules {
+    // What next_target trusts: whatever the block says
+    Unchecked,
+    // Later than the median of the last 11 blocks, at most MAX_FUTURE_DRIFT ahead
+    MedianTimePast,
+}
+
+impl TimestampRules {
+    fn name(self) -> &'static str {
+        match self {
+            TimestampRules::Unchecked => "no timestamp rules",
+            TimestampRules::MedianTimePast => "median time past + drift limit",
+        }
+    }
+
+    fn allowed_range(self, timestamps: &[i64], now: i64) -> (i64, i64) {
+        match self {
+            TimestampRules::Unchecked => (timestamps[0], now + UNRULED_LEAD),
+            TimestampRules::MedianTimePast => {
+                (timestamps::earliest_allowed(timestamps), timestamps::latest_allowed(now))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeWarpReport {
+    pub blocks: usize,
+    // Real seconds the attacker needed for all its blocks
+    pub real_time: f64,
+    pub difficulty_ratio: f64,
+    // How far the last timestamp is ahead of the real clock
+    pub clock_lead: i64,
+}
+
+// An attacker with all the hash power mines `blocks` blocks and stamps each
+// one with whatever allowed timestamp makes the next block easiest. Time is
+// virtual: each block takes exactly its expected solve time at `hash_rate`.
+pub fn simulate(algorithm: RetargetAlgorithm, rules: TimestampRules, target_time: u64,
+                hash_rate: f64, blocks: usize) -> TimeWarpReport {
+    let strategy = algorithm.strategy();
+    let initial = Target::from_difficulty(hash_rate * target_time as f64);
+    let mut history: Vec<BlockTime> = (0..HONEST_BLOCKS)
+        .map(|i| BlockTime { timestamp: (i as u64 * target_time) as i64, target: initial })
+        .collect();
+    let start = history[HONEST_BLOCKS - 1].timestamp as f64;
+    let mut now = start;
+
+    for _ in 0..blocks {
+        let target = strategy.next_target(&history, history[history.len() - 1].target, target_time);
+        now += target.difficulty() / hash_rate;
+
+        let timestamps: Vec<i64> = history.iter().map(|block| block.timestamp).collect();
+        let (earliest, latest) = rules.allowed_range(&timestamps, now as i64);
+        let previous = timestamps[timestamps.len() - 1];
+        let candidates = (-12..=12)
+            .map(|k| previous + k * target_time as i64)
+            .chain([earliest, latest, now as i64])
+            .filter(|timestamp| (earliest..=latest).contains(timestamp));
+
+        // Greedy: the timestamp after which the next target is the easiest
+        let mut best: Option<(f64, i64)> = None;
+        for timestamp in candidates {
+            history.push(BlockTime { timestamp, target });
+            let next = strategy.next_target(&history, target, target_time).as_f64();
+            history.pop();
+            if best.is_none_or(|(easiest, _)| next > easiest) {
+                best = Some((next, timestamp));
+            }
+        }
+        let (_, timestamp) = best.expect("the allowed range always contains `latest`");
+        history.push(BlockTime { timestamp, target });
+    }
+
+    let last = &history[history.len() - 1];
+    TimeWarpReport {
+        blocks,
+        real_time: now - start,
+        difficulty_ratio: last.target.difficulty() / initial.difficulty(),
+        clock_lead: last.timestamp - now as i64,
+    }
+}
+
+pub fn display_time_warp(target_time: u64) {
+    let blocks = 200;
+    println!("\n⏳ TIME-WARP ATTACK: a miner with all the hash power forges timestamps for {} blocks", blocks);
+    println!("   Honest pace would be {} blocks in {}s", blocks, blocks as u64 * target_time);
+    println!("───────────────────────────");
+    for algorithm in RetargetAlgorithm::all() {
+        println!("📐 {}", algorithm.name());
+        for rules in [TimestampRules::Unchecked, TimestampRules::MedianTimePast] {
+            let report = simulate(algorithm, rules, target_time, 1_000_000.0, blocks);
+            println!("   {:<32} mined in {:>9.1}s real time ({:>7.2}s/block), difficulty x{:<10.4} timestamps {:>6}s ahead",
+                     rules.name(), report.real_time, report.real_time / report.blocks as f64,
+                     report.difficulty_ratio, report.clock_lead);
+        }
+    }
+    println!("───────────────────────────");
+    println!("Without rules the attacker stamps blocks far in the future to fake slow");
+    println!("blocks and mines at a fraction of the difficulty. The drift limit caps the");
+    println!("fake lead at {}s; what is left is the slack the retarget rule itself allows.\n",
+             timestamps::MAX_FUTURE_DRIFT);
+}
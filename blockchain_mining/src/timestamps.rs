@@ -0,0 +1,85 @@
+// Consensus rules for block timestamps, in whole seconds (the precision the
+// block hash commits to). A timestamp has to be later than the median of the
+// previous MEDIAN_TIME_SPAN blocks and no more than MAX_FUTURE_DRIFT seconds
+// ahead of the validating node's clock.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+// Bitcoin allows two hours, twelve of its blocks; twelve of our 10s blocks
+pub const MAX_FUTURE_DRIFT: i64 = 120;
+
+// Median of the last MEDIAN_TIME_SPAN timestamps (oldest first)
+pub fn median_time_past(timestamps: &[i64]) -> Option<i64> {
+    let start = timestamps.len().saturating_sub(MEDIAN_TIME_SPAN);
+    let mut recent = timestamps[start..].to_vec();
+    if recent.is_empty() {
+        return None;
+    }
+    recent.sort_unstable();
+    Some(recent[recent.len() / 2])
+}
+
+// Earliest timestamp the block after `timestamps` may carry
+pub fn earliest_allowed(timestamps: &[i64]) -> i64 {
+    median_time_past(timestamps).map_or(i64::MIN, |median| median + 1)
+}
+
+pub fn latest_allowed(now: i64) -> i64 {
+    now + MAX_FUTURE_DRIFT
+}
+
+pub fn check(timestamp: i64, previous: &[i64], now: i64) -> Result<(), String> {
+    let earliest = earliest_allowed(previous);
+    if timestamp < earliest {
+        return Err(format!("{} is not after the median time past {}", timestamp, earliest - 1));
+    }
+    if timestamp > latest_allowed(now) {
+        return Err(format!("{} is more than {}s in the future", timestamp, MAX_FUTURE_DRIFT));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_history_allows_any_past_timestamp() {
+        assert_eq!(median_time_past(&[]), None);
+        assert_eq!(earliest_allowed(&[]), i64::MIN);
+        assert!(check(0, &[], 1_000).is_ok());
+    }
+
+    #[test]
+    fn a_short_history_uses_every_timestamp() {
+        assert_eq!(median_time_past(&[50]), Some(50));
+        assert_eq!(median_time_past(&[30, 10, 20]), Some(20));
+    }
+
+    #[test]
+    fn an_even_history_takes_the_upper_middle() {
+        assert_eq!(median_time_past(&[10, 40]), Some(40));
+        assert_eq!(median_time_past(&[40, 10, 30, 20]), Some(30));
+    }
+
+    #[test]
+    fn only_the_last_span_counts() {
+        // Eleven late timestamps after two early ones; the early ones drop out
+        let mut timestamps = vec![0, 0];
+        timestamps.extend((1..=MEDIAN_TIME_SPAN as i64).map(|i| 100 + i));
+        assert_eq!(median_time_past(&timestamps), Some(106));
+    }
+
+    #[test]
+    fn the_timestamp_must_pass_the_median() {
+        let previous = [100, 110, 120];
+        assert_eq!(earliest_allowed(&previous), 111);
+        assert!(check(110, &previous, 200).is_err());
+        assert!(check(111, &previous, 200).is_ok());
+    }
+
+    #[test]
+    fn the_future_drift_boundary_is_inclusive() {
+        let now = 1_000;
+        assert!(check(now + MAX_FUTURE_DRIFT, &[], now).is_ok());
+        assert!(check(now + MAX_FUTURE_DRIFT + 1, &[], now).is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::wallet::{self, Wallet};
+use crate::{Block, BlockchainError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Sender of the transaction that pays the block reward. It may only appear
+// first in a block and is the only way coins come into existence.
+pub const COINBASE: &str = "coinbase";
+pub const BLOCK_REWARD: u64 = 50;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Transaction {
+    pub sender: String,
+    pub receiver: String,
+    pub amount: u64,
+    // Counts the sender's transactions from 0, so each can only be applied once
+    pub nonce: u64,
+    // Hex-encoded; empty on coinbase transactions, which nobody signs
+    #[serde(default)]
+    pub public_key: String,
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl Transaction {
+    // The reward for the block at `index`; the index keeps it unique
+    pub fn coinbase(receiver: String, index: u64) -> Self {
+        Self {
+            sender: COINBASE.to_string(),
+            receiver,
+            amount: BLOCK_REWARD,
+            nonce: index,
+            public_key: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    // Sent from the wallet's address
+    pub fn signed(wallet: &Wallet, receiver: String, amount: u64, nonce: u64) -> Self {
+        let mut transaction = Self {
+            sender: wallet.address(),
+            receiver,
+            amount,
+            nonce,
+            public_key: wallet.public_key(),
+            signature: String::new(),
+        };
+        transaction.signature = wallet.sign(&transaction.signing_message());
+        transaction
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.sender == COINBASE
+    }
+
+    // Everything but the signature
+    pub fn signing_message(&self) -> String {
+        format!(
+            "{}>{}:{}#{}",
+            self.sender, self.receiver, self.amount, self.nonce
+        )
+    }
+
+    // What the block hash commits to
+    pub fn hash_input(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            self.signing_message(),
+            self.public_key,
+            self.signature
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Account {
+    pub balance: u64,
+    // The nonce the account's next transaction must carry
+    pub nonce: u64,
+}
+
+// Balances of every account, rebuilt by replaying the chain from genesis
+#[derive(Clone, Debug, Default)]
+pub struct Ledger {
+    accounts: HashMap<String, Account>,
+}
+
+impl Ledger {
+    pub fn from_chain(chain: &[Block]) -> Result<Self> {
+        let mut ledger = Self::default();
+        for block in chain {
+            ledger.apply_block(block)?;
+        }
+        Ok(ledger)
+    }
+
+    // All of the block's transactions or none of them
+    pub fn apply_block(&mut self, block: &Block) -> Result<()> {
+        let mut next = self.clone();
+        for (position, transaction) in block.transactions.iter().enumerate() {
+            next.apply(block.index, position, transaction)?;
+        }
+        *self = next;
+        Ok(())
+    }
+
+    fn apply(&mut self, index: u64, position: usize, transaction: &Transaction) -> Result<()> {
+        let invalid = |message: &str| BlockchainError::InvalidTransaction {
+            index,
+            message: message.to_string(),
+        };
+        if transaction.amount == 0 {
+            return Err(invalid("amount must be positive"));
+        }
+
+        if transaction.is_coinbase() {
+            if position != 0 {
+                return Err(invalid("only the first transaction may be a coinbase"));
+            }
+            if transaction.nonce != index {
+                return Err(invalid("coinbase nonce must be the block index"));
+            }
+            if transaction.amount > BLOCK_REWARD {
+                return Err(invalid("coinbase pays more than the block reward"));
+            }
+        } else {
+            if !wallet::verify(
+                &transaction.sender,
+                &transaction.public_key,
+                &transaction.signature,
+                &transaction.signing_message(),
+            ) {
+                return Err(BlockchainError::InvalidSignature {
+                    index,
+                    signer: transaction.sender.clone(),
+                });
+            }
+            let sender = self.accounts.entry(transaction.sender.clone()).or_default();
+            if transaction.nonce != sender.nonce {
+                return Err(BlockchainError::InvalidNonce {
+                    account: transaction.sender.clone(),
+                    expected: sender.nonce,
+                    found: transaction.nonce,
+                });
+            }
+            if transaction.amount > sender.balance {
+                return Err(BlockchainError::InsufficientFunds {
+                    account: transaction.sender.clone(),
+                    balance: sender.balance,
+                    amount: transaction.amount,
+                });
+            }
+            sender.balance -= transaction.amount;
+            sender.nonce += 1;
+        }
+
+        let receiver = self
+            .accounts
+            .entry(transaction.receiver.clone())
+            .or_default();
+        receiver.balance = receiver
+            .balance
+            .checked_add(transaction.amount)
+            .ok_or_else(|| invalid("receiver balance overflows"))?;
+        Ok(())
+    }
+
+    pub fn next_nonce(&self, account: &str) -> u64 {
+        self.accounts.get(account).map_or(0, |a| a.nonce)
+    }
+
+    // Sorted by name
+    pub fn accounts(&self) -> Vec<(&String, &Account)> {
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_by(|a, b| a.0.cmp(b.0));
+        accounts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(index: u64, transactions: Vec<Transaction>) -> Block {
+        Block {
+            index,
+            transactions,
+            ..Block::default()
+        }
+    }
+
+    // A ledger where `wallet` has mined the reward of block 1
+    fn funded(wallet: &Wallet) -> Ledger {
+        let mut ledger = Ledger::default();
+        ledger
+            .apply_block(&block(1, vec![Transaction::coinbase(wallet.address(), 1)]))
+            .unwrap();
+        ledger
+    }
+
+    fn balance(ledger: &Ledger, account: &str) -> u64 {
+        ledger.accounts.get(account).map_or(0, |a| a.balance)
+    }
+
+    #[test]
+    fn transfers_move_coins_and_bump_the_nonce() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut ledger = funded(&alice);
+        let payment = Transaction::signed(&alice, bob.address(), 20, 0);
+        ledger.apply_block(&block(2, vec![payment])).unwrap();
+
+        assert_eq!(balance(&ledger, &alice.address()), BLOCK_REWARD - 20);
+        assert_eq!(balance(&ledger, &bob.address()), 20);
+        assert_eq!(ledger.next_nonce(&alice.address()), 1);
+    }
+
+    #[test]
+    fn overspending_is_rejected() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut ledger = funded(&alice);
+        let payment = Transaction::signed(&alice, bob.address(), BLOCK_REWARD + 1, 0);
+        let result = ledger.apply_block(&block(2, vec![payment]));
+        assert!(matches!(
+            result,
+            Err(BlockchainError::InsufficientFunds {
+                balance: BLOCK_REWARD,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn a_replayed_transaction_has_a_stale_nonce() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut ledger = funded(&alice);
+        let payment = Transaction::signed(&alice, bob.address(), 10, 0);
+        ledger
+            .apply_block(&block(2, vec![payment.clone()]))
+            .unwrap();
+
+        let result = ledger.apply_block(&block(3, vec![payment]));
+        assert!(matches!(
+            result,
+            Err(BlockchainError::InvalidNonce {
+                expected: 1,
+                found: 0,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn the_coinbase_must_come_first() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let miner = Wallet::generate("miner".to_string());
+        let mut ledger = funded(&alice);
+        let transactions = vec![
+            Transaction::signed(&alice, bob.address(), 10, 0),
+            Transaction::coinbase(miner.address(), 2),
+        ];
+        let result = ledger.apply_block(&block(2, transactions));
+        assert!(matches!(
+            result,
+            Err(BlockchainError::InvalidTransaction { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn the_coinbase_cant_pay_more_than_the_reward() {
+        let miner = Wallet::generate("miner".to_string());
+        let mut coinbase = Transaction::coinbase(miner.address(), 1);
+        coinbase.amount = BLOCK_REWARD + 1;
+        let result = Ledger::default().apply_block(&block(1, vec![coinbase]));
+        assert!(matches!(
+            result,
+            Err(BlockchainError::InvalidTransaction { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn a_failed_block_changes_nothing() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut ledger = funded(&alice);
+        let transactions = vec![
+            Transaction::signed(&alice, bob.address(), 30, 0),
+            Transaction::signed(&alice, bob.address(), 30, 1),
+        ];
+        assert!(ledger.apply_block(&block(2, transactions)).is_err());
+        assert_eq!(balance(&ledger, &alice.address()), BLOCK_REWARD);
+        assert_eq!(ledger.next_nonce(&alice.address()), 0);
+    }
+
+    #[test]
+    fn transactions_must_be_signed_by_the_sender() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mallory = Wallet::generate("mallory".to_string());
+        let mut ledger = funded(&alice);
+
+        let mut tampered = Transaction::signed(&alice, bob.address(), 10, 0);
+        tampered.receiver = mallory.address();
+        let mut forged = Transaction::signed(&mallory, mallory.address(), 10, 0);
+        forged.sender = alice.address();
+        for transaction in [tampered, forged] {
+            let result = ledger.apply_block(&block(2, vec![transaction]));
+            assert!(matches!(
+                result,
+                Err(BlockchainError::InvalidSignature { index: 2, .. })
+            ));
+        }
+    }
+}
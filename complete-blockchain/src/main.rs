@@ -1,9 +1,16 @@
+mod ledger;
+mod utxo;
+mod wallet;
+
 use chrono::{DateTime, Utc};
+use ledger::{Ledger, Transaction, BLOCK_REWARD};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::io;
 use thiserror::Error;
+use utxo::{Checkpoints, UtxoSet, UtxoSnapshot, UtxoTransaction};
+use wallet::{Keystore, Wallet, KEYSTORE_FILE};
 
 #[derive(Error, Debug)]
 enum BlockchainError {
@@ -24,15 +31,86 @@ enum BlockchainError {
 
     #[error("Invalid block: {index}: {message}")]
     InvalidBlock { index: u64, message: String },
+
+    #[error("Block {index} has no valid proof of work: hash {hash} needs {difficulty} leading zeros")]
+    InsufficientProofOfWork {
+        index: u64,
+        hash: String,
+        difficulty: u64,
+    },
+
+    #[error("Difficulty {difficulty} is below the minimum of {minimum}")]
+    DifficultyTooLow { difficulty: u64, minimum: u64 },
+
+    #[error("Insufficient funds: {account} has {balance} but tries to send {amount}")]
+    InsufficientFunds {
+        account: String,
+        balance: u64,
+        amount: u64,
+    },
+
+    #[error("Invalid nonce for {account}: expected {expected}, found {found} (replayed transaction?)")]
+    InvalidNonce {
+        account: String,
+        expected: u64,
+        found: u64,
+    },
+
+    #[error("Invalid transaction in block {index}: {message}")]
+    InvalidTransaction { index: u64, message: String },
+
+    #[error("Double spend: output {output} is already spent or never existed")]
+    DoubleSpend { output: String },
+
+    #[error("Transaction {txid} creates {outputs} coins from inputs worth {inputs}")]
+    OutputsExceedInputs {
+        txid: String,
+        inputs: u64,
+        outputs: u64,
+    },
+
+    #[error("Block {index} commits to a different UTXO set")]
+    UtxoRootMismatch { index: u64 },
+
+    #[error("Invalid signature in block {index}: not signed by {signer}")]
+    InvalidSignature { index: u64, signer: String },
+
+    #[error("Wallet error: {0}")]
+    WalletError(String),
 }
 
 type Result<T> = std::result::Result<T, BlockchainError>;
 
+// Leading zero hex digits a block hash needs. New chains start here; a saved
+// chain may ask for more but not less, or rewriting blockchain.json would be
+// free again.
+const MIN_DIFFICULTY: u64 = 2;
+
+// Where this node pins the UTXO roots it computed itself
+const UTXO_CHECKPOINT_FILE: &str = "utxo_checkpoints.json";
+
+// How a chain tracks who owns what: account balances with nonces, or a set
+// of unspent transaction outputs. Fixed when the chain is created.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum LedgerModel {
+    #[default]
+    Account,
+    Utxo,
+}
+
 #[derive(Serialize, Deserialize, Clone, Default)]
 struct Block {
     index: u64,
     timestamp: DateTime<Utc>,
     data: String,
+    #[serde(default)]
+    transactions: Vec<Transaction>,
+    #[serde(default)]
+    utxo_transactions: Vec<UtxoTransaction>,
+    // Hash of the UTXO set after this block; empty on account chains
+    #[serde(default)]
+    utxo_root: String,
     previous_hash: String,
     hash: String,
     nonce: u64,
@@ -40,45 +118,70 @@ struct Block {
 
 impl Block {
     fn new(index: u64, data: String, previous_hash: String) -> Self {
-        let timestamp = Utc::now();
-        let nonce = 0;
-        let hash = Self::calculate_hash(index, &timestamp, &data, &previous_hash, nonce);
-        Self {
+        let mut block = Self {
             index,
-            timestamp,
+            timestamp: Utc::now(),
             data,
             previous_hash,
-            hash,
-            nonce,
-        }
+            ..Self::default()
+        };
+        block.hash = block.calculate_hash();
+        block
     }
 
-    fn genesis() -> Self {
-        Self::new(0, "Genesis Block".to_string(), "0".to_string())
+    fn genesis(difficulty: u64, model: LedgerModel) -> Self {
+        let mut block = Self::new(0, "Genesis Block".to_string(), "0".to_string());
+        if model == LedgerModel::Utxo {
+            block.utxo_root = UtxoSet::default().root();
+        }
+        block.mine(difficulty);
+        block
     }
 
-    fn calculate_hash(
-        index: u64,
-        timestamp: &DateTime<Utc>,
-        data: &String,
-        previous_hash: &String,
-        nonce: u64,
-    ) -> String {
+    fn calculate_hash(&self) -> String {
+        let transactions: Vec<String> = self.transactions.iter().map(Transaction::hash_input).collect();
+        let utxo_transactions: Vec<String> = self
+            .utxo_transactions
+            .iter()
+            .map(UtxoTransaction::hash_input)
+            .collect();
         let mut hasher = Sha256::new();
-        hasher
-            .update(format!("{}{}{}{}{}", index, timestamp, data, previous_hash, nonce).as_bytes());
+        hasher.update(
+            format!(
+                "{}{}{}[{}][{}]{}{}{}",
+                self.index,
+                self.timestamp,
+                self.data,
+                transactions.join(";"),
+                utxo_transactions.join(";"),
+                self.utxo_root,
+                self.previous_hash,
+                self.nonce
+            )
+            .as_bytes(),
+        );
         format!("{:x}", hasher.finalize())
     }
 
+    // Try nonces from 0 until the hash has `difficulty` leading zeros
+    fn mine(&mut self, difficulty: u64) {
+        self.nonce = 0;
+        loop {
+            self.hash = self.calculate_hash();
+            if self.meets_difficulty(difficulty) {
+                return;
+            }
+            self.nonce += 1;
+        }
+    }
+
+    fn meets_difficulty(&self, difficulty: u64) -> bool {
+        self.hash.len() as u64 >= difficulty
+            && self.hash.chars().take(difficulty as usize).all(|c| c == '0')
+    }
+
     fn is_valid(&self) -> bool {
-        let hash = Self::calculate_hash(
-            self.index,
-            &self.timestamp,
-            &self.data,
-            &self.previous_hash,
-            self.nonce,
-        );
-        hash == self.hash
+        self.calculate_hash() == self.hash
     }
 
     fn show_info(&self) {
@@ -91,8 +194,33 @@ impl Block {
             self.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
         );
         println!("| Data: {}", self.data);
+        for transaction in &self.transactions {
+            println!(
+                "| Tx: {} -> {}: {} (nonce {})",
+                transaction.sender, transaction.receiver, transaction.amount, transaction.nonce
+            );
+        }
+        for transaction in &self.utxo_transactions {
+            let txid = transaction.txid();
+            let inputs: Vec<String> = transaction.inputs.iter().map(|i| i.to_string()).collect();
+            let outputs: Vec<String> = transaction
+                .outputs
+                .iter()
+                .map(|o| format!("{} {}", o.owner, o.amount))
+                .collect();
+            let inputs = if transaction.coinbase.is_some() {
+                "coinbase".to_string()
+            } else {
+                inputs.join(", ")
+            };
+            println!("| Tx {}: {} -> {}", &txid[..16], inputs, outputs.join(", "));
+        }
+        if !self.utxo_root.is_empty() {
+            println!("| UTXO Root: {}", &self.utxo_root[..self.utxo_root.len().min(16)]);
+        }
         println!("| Previous Hash: {}", &self.previous_hash[..self.previous_hash.len().min(16)]);
         println!("| Hash: {}", &self.hash[..self.hash.len().min(16)]);
+        println!("| Nonce: {}", self.nonce);
         println!("| Valid: {}", if self.is_valid() { "✓" } else { "✗" });
         println!("└─────────────────────────────────────────────────");
     }
@@ -102,15 +230,29 @@ impl Block {
 struct Blockchain {
     chain: Vec<Block>,
     difficulty: u64,
+    #[serde(default)]
+    model: LedgerModel,
+    // Derived from the chain, so rebuilt on load rather than saved
+    #[serde(skip)]
+    ledger: Ledger,
+    #[serde(skip)]
+    utxos: UtxoSet,
+    // The UTXO set as of the last save, so loading needn't replay every block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    utxo_snapshot: Option<UtxoSnapshot>,
 }
 
 impl Blockchain {
-    fn new() -> Self {
+    fn new(model: LedgerModel) -> Self {
         let mut blockchain = Self {
             chain: Vec::new(),
-            difficulty: 2,
+            difficulty: MIN_DIFFICULTY,
+            model,
+            ledger: Ledger::default(),
+            utxos: UtxoSet::default(),
+            utxo_snapshot: None,
         };
-        blockchain.chain.push(Block::genesis());
+        blockchain.chain.push(Block::genesis(blockchain.difficulty, model));
         blockchain
     }
 
@@ -126,20 +268,38 @@ impl Blockchain {
         block.index = last_block.index + 1;
         block.previous_hash = last_block.hash.clone();
         block.timestamp = Utc::now();
-        block.nonce = 0;
-        block.hash = Block::calculate_hash(
-            block.index,
-            &block.timestamp,
-            &block.data,
-            &block.previous_hash,
-            block.nonce,
-        );
+
+        // The block commits to the UTXO set it leaves behind, so work that
+        // out before mining
+        let mut utxos = self.utxos.clone();
+        if self.model == LedgerModel::Utxo {
+            utxos.apply_block(&block)?;
+            block.utxo_root = utxos.root();
+        }
+        block.mine(self.difficulty);
 
         self.validate_new_block(&block)?;
+        self.ledger.apply_block(&block)?;
+        self.utxos = utxos;
         self.chain.push(block);
         Ok(())
     }
 
+    // Each chain only carries the transactions of its own ledger model
+    fn check_model(&self, block: &Block) -> Result<()> {
+        let mixed = match self.model {
+            LedgerModel::Account => !block.utxo_transactions.is_empty() || !block.utxo_root.is_empty(),
+            LedgerModel::Utxo => !block.transactions.is_empty(),
+        };
+        if mixed {
+            return Err(BlockchainError::InvalidBlock {
+                index: block.index,
+                message: format!("transactions don't match the {:?} ledger model", self.model),
+            });
+        }
+        Ok(())
+    }
+
     fn validate_new_block(&self, block: &Block) -> Result<()> {
         let last_block = self.last_block().ok_or(BlockchainError::InvalidBlock {
             index: 0,
@@ -169,10 +329,58 @@ impl Blockchain {
             });
         }
 
+        // Verify the proof of work
+        if !block.meets_difficulty(self.difficulty) {
+            return Err(BlockchainError::InsufficientProofOfWork {
+                index: block.index,
+                hash: block.hash.clone(),
+                difficulty: self.difficulty,
+            });
+        }
+
+        // Verify the transactions against current balances and nonces, or
+        // against the unspent outputs
+        self.check_model(block)?;
+        match self.model {
+            LedgerModel::Account => self.ledger.clone().apply_block(block)?,
+            LedgerModel::Utxo => {
+                let mut utxos = self.utxos.clone();
+                utxos.apply_block(block)?;
+                if utxos.root() != block.utxo_root {
+                    return Err(BlockchainError::UtxoRootMismatch { index: block.index });
+                }
+            }
+        }
+
         Ok(())
     }
 
     fn is_chain_valid(&self) -> bool {
+        if !self.are_blocks_valid() {
+            return false;
+        }
+
+        let replayed = match self.model {
+            LedgerModel::Account => Ledger::from_chain(&self.chain).map(|_| ()),
+            LedgerModel::Utxo => UtxoSet::from_chain(&self.chain).map(|_| ()),
+        };
+        if let Err(e) = replayed {
+            println!("Invalid transactions: {}", e);
+            return false;
+        }
+        true
+    }
+
+    // Hashes, links, proof of work and ledger model; everything short of
+    // replaying the transactions
+    fn are_blocks_valid(&self) -> bool {
+        if let Some(genesis) = self.chain.first() {
+            if !genesis.is_valid() || !genesis.meets_difficulty(self.difficulty) {
+                println!("Invalid genesis block");
+                return false;
+            }
+        }
+
         for i in 1..self.chain.len() {
             let current_block = &self.chain[i];
             let previous_block = &self.chain[i - 1];
@@ -186,10 +394,88 @@ impl Blockchain {
                 println!("Previous hash mismatch at block {}", current_block.index);
                 return false;
             }
+
+            if !current_block.meets_difficulty(self.difficulty) {
+                println!("Missing proof of work at block {}", current_block.index);
+                return false;
+            }
+        }
+
+        for block in &self.chain {
+            if let Err(e) = self.check_model(block) {
+                println!("{}", e);
+                return false;
+            }
         }
         true
     }
 
+    // Nonce for `account`'s next transaction, counting `pending` ones that
+    // aren't in a block yet
+    fn next_nonce(&self, account: &str, pending: &[Transaction]) -> u64 {
+        let queued = pending.iter().filter(|t| t.sender == account).count();
+        self.ledger.next_nonce(account) + queued as u64
+    }
+
+    // Pays `amount` from the wallet's unspent outputs, leaving alone the
+    // ones `pending` transactions already spend
+    fn transfer(
+        &self,
+        wallet: &Wallet,
+        receiver: &str,
+        amount: u64,
+        pending: &[UtxoTransaction],
+    ) -> Result<UtxoTransaction> {
+        let mut utxos = self.utxos.clone();
+        let index = self.chain.len() as u64;
+        for transaction in pending.iter().filter(|t| t.coinbase.is_none()) {
+            utxos.apply_transaction(index, transaction)?;
+        }
+        let mut transaction = utxos.transfer(&wallet.address(), receiver, amount)?;
+        transaction.sign(wallet);
+        Ok(transaction)
+    }
+
+    // Signs a payment with `wallet` and queues it behind the block's
+    // pending transactions of whichever ledger model the chain uses
+    fn queue_payment(
+        &self,
+        wallet: &Wallet,
+        receiver: &str,
+        amount: u64,
+        transactions: &mut Vec<Transaction>,
+        utxo_transactions: &mut Vec<UtxoTransaction>,
+    ) -> Result<()> {
+        // Checked before signing: nobody could ever spend what a typo receives
+        if !wallet::is_address(receiver) {
+            return Err(BlockchainError::WalletError(format!("{} is not an address", receiver)));
+        }
+        match self.model {
+            LedgerModel::Account => {
+                let nonce = self.next_nonce(&wallet.address(), transactions);
+                transactions.push(Transaction::signed(wallet, receiver.to_string(), amount, nonce));
+            }
+            LedgerModel::Utxo => {
+                let transaction = self.transfer(wallet, receiver, amount, utxo_transactions)?;
+                utxo_transactions.push(transaction);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_coinbase(
+        &self,
+        miner: &str,
+        transactions: &mut Vec<Transaction>,
+        utxo_transactions: &mut Vec<UtxoTransaction>,
+    ) {
+        let index = self.chain.len() as u64;
+        match self.model {
+            LedgerModel::Account => transactions.push(Transaction::coinbase(miner.to_string(), index)),
+            LedgerModel::Utxo => utxo_transactions.push(UtxoTransaction::coinbase(miner.to_string(), index)),
+        }
+    }
+
     fn search_blocks(&self, text: &str) -> Vec<&Block> {
         self.chain
             .iter()
@@ -229,33 +515,114 @@ impl Blockchain {
         }
     }
 
-    fn save_file(&self, path: &str) -> Result<()> {
+    fn save_file(&mut self, path: &str) -> Result<()> {
+        if self.model == LedgerModel::Utxo {
+            let height = self.chain.len() as u64 - 1;
+            self.utxo_snapshot = Some(self.utxos.snapshot(height));
+            self.record_checkpoint()?;
+        }
         let json = serde_json::to_string_pretty(self)?;
         fs::write(path, json)?;
         println!("Blockchain saved to {}", path);
         Ok(())
     }
 
+    // Pins the root of our UTXO set, which only ever comes from blocks we
+    // validated, so a later load can trust a snapshot of it
+    fn record_checkpoint(&self) -> Result<()> {
+        let Some(tip) = self.last_block() else {
+            return Ok(());
+        };
+        let mut checkpoints = Checkpoints::load(UTXO_CHECKPOINT_FILE);
+        checkpoints.record(&tip.hash, self.utxos.root());
+        checkpoints.save(UTXO_CHECKPOINT_FILE)
+    }
+
     fn load_file(path: &str) -> Result<Self> {
         let content = fs::read_to_string(path)?;
-        let blockchain: Self = serde_json::from_str(&content)?;
+        let mut blockchain: Self = serde_json::from_str(&content)?;
+
+        if blockchain.difficulty < MIN_DIFFICULTY {
+            return Err(BlockchainError::DifficultyTooLow {
+                difficulty: blockchain.difficulty,
+                minimum: MIN_DIFFICULTY,
+            });
+        }
         
-        if !blockchain.is_chain_valid() {
+        if !blockchain.are_blocks_valid() {
             return Err(BlockchainError::InvalidBlock {
                 index: 0,
                 message: "Invalid blockchain".to_string(),
             });
         }
 
+        match blockchain.model {
+            LedgerModel::Account => blockchain.ledger = Ledger::from_chain(&blockchain.chain)?,
+            LedgerModel::Utxo => {
+                // The snapshot is only trusted if we pinned its root ourselves;
+                // otherwise replay everything once and pin the result
+                let checkpoints = Checkpoints::load(UTXO_CHECKPOINT_FILE);
+                let restored = match blockchain.utxo_snapshot.take() {
+                    Some(snapshot) => {
+                        let height = snapshot.height;
+                        let restored = UtxoSet::from_snapshot(snapshot, &blockchain.chain, &checkpoints)?;
+                        match &restored {
+                            Some(_) => println!(
+                                "UTXO set restored from the snapshot at block {}, replayed {} blocks",
+                                height,
+                                blockchain.chain.len() as u64 - height - 1
+                            ),
+                            None => println!("UTXO snapshot isn't one this node checkpointed, replaying from genesis"),
+                        }
+                        restored
+                    }
+                    None => None,
+                };
+                match restored {
+                    Some(utxos) => blockchain.utxos = utxos,
+                    None => {
+                        blockchain.utxos = UtxoSet::from_chain(&blockchain.chain)?;
+                        blockchain.record_checkpoint()?;
+                    }
+                }
+            }
+        }
+
         println!("Blockchain loaded from {}", path);
         Ok(blockchain)
     }
 }
 
+// Prints `message` and reads the trimmed reply
+fn prompt(message: &str) -> io::Result<String> {
+    println!("{}", message);
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+// An address with the name of its wallet, if it's one of ours
+fn label(keystore: &Keystore, address: &str) -> String {
+    match keystore.name_of(address) {
+        Some(name) => format!("{} ({})", address, name),
+        None => address.to_string(),
+    }
+}
+
 fn main() -> Result<()> {
     println!("===Program to simulate a blockchain===\n");
 
-    let mut blockchain = Blockchain::new();
+    let mut blockchain = Blockchain::new(LedgerModel::Account);
+    let mut keystore = match Keystore::load(KEYSTORE_FILE) {
+        Ok(keystore) => keystore,
+        // Carrying on with an empty keystore would overwrite the file, and
+        // every key in it, as soon as a wallet is created
+        Err(e) => {
+            println!("Error loading {}: {}", KEYSTORE_FILE, e);
+            println!("Repair or move it aside, then start again.");
+            return Err(e);
+        }
+    };
 
     println!("\n===Interactive Menu===\n");
     println!("1. Add block\n");
@@ -265,7 +632,11 @@ fn main() -> Result<()> {
     println!("5. Statistics\n");
     println!("6. Save blockchain\n");
     println!("7. Load blockchain\n");
-    println!("8. Exit\n");
+    println!("8. Show balances\n");
+    println!("9. New blockchain (account or UTXO ledger)\n");
+    println!("10. Create wallet\n");
+    println!("11. Send funds\n");
+    println!("12. Exit\n");
 
     loop {
         println!("Enter your choice: ");
@@ -286,8 +657,66 @@ fn main() -> Result<()> {
                     continue;
                 }
                 let data = data.trim().to_string();
-                
-                match blockchain.add_block(Block { data, ..Block::default() }) {
+
+                println!("Enter miner wallet or address (receives {} coins): ", BLOCK_REWARD);
+                let mut miner = String::new();
+                if let Err(e) = io::stdin().read_line(&mut miner) {
+                    println!("Error reading input: {}", e);
+                    continue;
+                }
+                let mut transactions = Vec::new();
+                let mut utxo_transactions = Vec::new();
+                let miner = miner.trim();
+                if !miner.is_empty() {
+                    match keystore.resolve(miner) {
+                        Ok(miner) => blockchain.add_coinbase(&miner, &mut transactions, &mut utxo_transactions),
+                        Err(e) => {
+                            println!("Error: {}", e);
+                            continue;
+                        }
+                    }
+                }
+
+                println!("Enter transactions as 'sender_wallet receiver amount', each followed by the wallet password, empty line to finish: ");
+                loop {
+                    let mut line = String::new();
+                    if let Err(e) = io::stdin().read_line(&mut line) {
+                        println!("Error reading input: {}", e);
+                        break;
+                    }
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    match parts.as_slice() {
+                        [] => break,
+                        [sender, receiver, amount] => match amount.parse::<u64>() {
+                            Ok(amount) => {
+                                let queued = keystore.resolve(receiver).and_then(|receiver| {
+                                    let password = prompt(&format!("Password for {}: ", sender))?;
+                                    let wallet = keystore.unlock(sender, &password)?;
+                                    blockchain.queue_payment(
+                                        &wallet,
+                                        &receiver,
+                                        amount,
+                                        &mut transactions,
+                                        &mut utxo_transactions,
+                                    )
+                                });
+                                if let Err(e) = queued {
+                                    println!("Skipping transaction: {}", e);
+                                }
+                            }
+                            Err(_) => println!("Invalid amount: {}", amount),
+                        },
+                        _ => println!("Expected 'sender receiver amount'"),
+                    }
+                }
+
+                let block = Block {
+                    data,
+                    transactions,
+                    utxo_transactions,
+                    ..Block::default()
+                };
+                match blockchain.add_block(block) {
                     Ok(_) => println!("Block added successfully!"),
                     Err(e) => println!("Error adding block: {}", e),
                 }
@@ -339,7 +768,91 @@ fn main() -> Result<()> {
                     Err(e) => println!("Error loading blockchain: {}", e),
                 }
             }
-            "8" => {
+            "8" => match blockchain.model {
+                LedgerModel::Account => {
+                    let accounts = blockchain.ledger.accounts();
+                    if accounts.is_empty() {
+                        println!("No accounts yet");
+                    }
+                    for (address, account) in accounts {
+                        println!("{}: {} coins (next nonce {})", label(&keystore, address), account.balance, account.nonce);
+                    }
+                }
+                LedgerModel::Utxo => {
+                    let balances = blockchain.utxos.balances();
+                    if balances.is_empty() {
+                        println!("No unspent outputs yet");
+                    }
+                    for (owner, (total, outputs)) in balances {
+                        println!("{}: {} coins in {} unspent outputs", label(&keystore, owner), total, outputs);
+                    }
+                }
+            },
+            "9" => {
+                println!("Ledger model (account/utxo): ");
+                let mut model = String::new();
+                if let Err(e) = io::stdin().read_line(&mut model) {
+                    println!("Error reading input: {}", e);
+                    continue;
+                }
+                let model = match model.trim().to_lowercase().as_str() {
+                    "account" => LedgerModel::Account,
+                    "utxo" => LedgerModel::Utxo,
+                    other => {
+                        println!("Unknown ledger model: {}", other);
+                        continue;
+                    }
+                };
+                blockchain = Blockchain::new(model);
+                println!("Started a new {:?} blockchain", model);
+            }
+            "10" => {
+                let created = (|| -> Result<Wallet> {
+                    let name = prompt("Wallet name: ")?;
+                    if name.is_empty() || name.contains(char::is_whitespace) {
+                        return Err(BlockchainError::WalletError("name must be one word".to_string()));
+                    }
+                    let password = prompt("Password to encrypt the key with: ")?;
+                    let wallet = Wallet::generate(name);
+                    keystore.add(&wallet, &password)?;
+                    keystore.save(KEYSTORE_FILE)?;
+                    Ok(wallet)
+                })();
+                match created {
+                    Ok(wallet) => println!("Wallet {} created with address {}", wallet.name, wallet.address()),
+                    Err(e) => println!("Error creating wallet: {}", e),
+                }
+            }
+            "11" => {
+                let sent = (|| -> Result<()> {
+                    let sender = prompt("From wallet: ")?;
+                    let wallet = keystore.unlock(&sender, &prompt("Password: ")?)?;
+                    let receiver = keystore.resolve(&prompt("To wallet or address: ")?)?;
+                    let amount = prompt("Amount: ")?;
+                    let amount = amount
+                        .parse::<u64>()
+                        .map_err(|_| BlockchainError::WalletError(format!("invalid amount: {}", amount)))?;
+                    let miner = prompt(&format!("Miner wallet or address (receives {} coins, empty for none): ", BLOCK_REWARD))?;
+
+                    let mut transactions = Vec::new();
+                    let mut utxo_transactions = Vec::new();
+                    if !miner.is_empty() {
+                        blockchain.add_coinbase(&keystore.resolve(&miner)?, &mut transactions, &mut utxo_transactions);
+                    }
+                    blockchain.queue_payment(&wallet, &receiver, amount, &mut transactions, &mut utxo_transactions)?;
+                    blockchain.add_block(Block {
+                        data: format!("{} sends {} to {}", wallet.name, amount, receiver),
+                        transactions,
+                        utxo_transactions,
+                        ..Block::default()
+                    })
+                })();
+                match sent {
+                    Ok(_) => println!("Funds sent!"),
+                    Err(e) => println!("Error sending funds: {}", e),
+                }
+            }
+            "12" => {
                 println!("Goodbye!");
                 break;
             },
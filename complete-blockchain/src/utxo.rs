@@ -0,0 +1,597 @@
+use crate::ledger::BLOCK_REWARD;
+use crate::wallet::{self, Wallet};
+use crate::{Block, BlockchainError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+// Points at one output of an earlier transaction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OutPoint {
+    pub txid: String,
+    pub index: u32,
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            &self.txid[..self.txid.len().min(16)],
+            self.index
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TxOutput {
+    pub owner: String,
+    pub amount: u64,
+}
+
+// Spends whole outputs and creates new ones; whatever the outputs don't
+// claim is a fee for the miner
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UtxoTransaction {
+    pub inputs: Vec<OutPoint>,
+    pub outputs: Vec<TxOutput>,
+    // Set to the block index on the block reward, which has no inputs
+    #[serde(default)]
+    pub coinbase: Option<u64>,
+    // Hex-encoded; the key's address must own every input
+    #[serde(default)]
+    pub public_key: String,
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl UtxoTransaction {
+    pub fn coinbase(owner: String, index: u64) -> Self {
+        Self {
+            inputs: Vec::new(),
+            outputs: vec![TxOutput {
+                owner,
+                amount: BLOCK_REWARD,
+            }],
+            coinbase: Some(index),
+            public_key: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    pub fn sign(&mut self, wallet: &Wallet) {
+        self.public_key = wallet.public_key();
+        self.signature = wallet.sign(&self.signing_message());
+    }
+
+    // Everything but the signature
+    pub fn signing_message(&self) -> String {
+        let inputs: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|input| format!("{}:{}", input.txid, input.index))
+            .collect();
+        let outputs: Vec<String> = self
+            .outputs
+            .iter()
+            .map(|output| format!("{}:{}", output.owner, output.amount))
+            .collect();
+        format!(
+            "{}>{}#{:?}",
+            inputs.join(","),
+            outputs.join(","),
+            self.coinbase
+        )
+    }
+
+    // What the block hash and the txid commit to
+    pub fn hash_input(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            self.signing_message(),
+            self.public_key,
+            self.signature
+        )
+    }
+
+    pub fn txid(&self) -> String {
+        format!("{:x}", Sha256::digest(self.hash_input().as_bytes()))
+    }
+}
+
+// Where a saved UTXO set stands, so loading can skip replaying the blocks
+// up to `height`. Only trusted if this node has a checkpoint for that block.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UtxoSnapshot {
+    pub height: u64,
+    pub unspent: Vec<(OutPoint, TxOutput)>,
+}
+
+// UTXO roots this node worked out itself, by block hash. Kept apart from the
+// chain file: a root in a block only proves that someone mined it, which at
+// this difficulty anyone can redo after editing the snapshot to match.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Checkpoints {
+    roots: BTreeMap<String, String>,
+}
+
+impl Checkpoints {
+    // Empty if the file is missing or unreadable, which only costs a replay
+    pub fn load(path: &str) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, block_hash: &str, root: String) {
+        self.roots.insert(block_hash.to_string(), root);
+    }
+
+    pub fn root(&self, block_hash: &str) -> Option<&str> {
+        self.roots.get(block_hash).map(String::as_str)
+    }
+}
+
+// Every output not spent yet
+#[derive(Clone, Debug, Default)]
+pub struct UtxoSet {
+    unspent: BTreeMap<OutPoint, TxOutput>,
+}
+
+impl UtxoSet {
+    pub fn from_chain(chain: &[Block]) -> Result<Self> {
+        let mut utxos = Self::default();
+        utxos.replay(chain)?;
+        Ok(utxos)
+    }
+
+    // Starts from `snapshot` if both the block at its height and one of our
+    // own checkpoints commit to it, replaying only the blocks after it.
+    // Returns None if the snapshot can't be trusted.
+    pub fn from_snapshot(
+        snapshot: UtxoSnapshot,
+        chain: &[Block],
+        checkpoints: &Checkpoints,
+    ) -> Result<Option<Self>> {
+        let height = snapshot.height as usize;
+        let Some(block) = chain.get(height) else {
+            return Ok(None);
+        };
+        let mut utxos = Self {
+            unspent: snapshot.unspent.into_iter().collect(),
+        };
+        let root = utxos.root();
+        if root != block.utxo_root || checkpoints.root(&block.hash) != Some(root.as_str()) {
+            return Ok(None);
+        }
+        utxos.replay(&chain[height + 1..])?;
+        Ok(Some(utxos))
+    }
+
+    pub fn snapshot(&self, height: u64) -> UtxoSnapshot {
+        UtxoSnapshot {
+            height,
+            unspent: self
+                .unspent
+                .iter()
+                .map(|(outpoint, output)| (outpoint.clone(), output.clone()))
+                .collect(),
+        }
+    }
+
+    fn replay(&mut self, blocks: &[Block]) -> Result<()> {
+        for block in blocks {
+            self.apply_block(block)?;
+            if self.root() != block.utxo_root {
+                return Err(BlockchainError::UtxoRootMismatch { index: block.index });
+            }
+        }
+        Ok(())
+    }
+
+    // All of the block's transactions or none of them
+    pub fn apply_block(&mut self, block: &Block) -> Result<()> {
+        let mut next = self.clone();
+        let mut fees = 0u64;
+        for (position, transaction) in block.utxo_transactions.iter().enumerate() {
+            if transaction.coinbase.is_some() {
+                if position != 0 {
+                    return Err(BlockchainError::InvalidTransaction {
+                        index: block.index,
+                        message: "only the first transaction may be a coinbase".to_string(),
+                    });
+                }
+                continue;
+            }
+            fees += next.apply_transaction(block.index, transaction)?;
+        }
+
+        // The reward goes last: it may claim the fees of everything after it
+        if let Some(coinbase) = block
+            .utxo_transactions
+            .first()
+            .filter(|t| t.coinbase.is_some())
+        {
+            let invalid = |message: &str| BlockchainError::InvalidTransaction {
+                index: block.index,
+                message: message.to_string(),
+            };
+            if coinbase.coinbase != Some(block.index) || !coinbase.inputs.is_empty() {
+                return Err(invalid("coinbase must name its block and have no inputs"));
+            }
+            let paid = total(&coinbase.outputs, block.index)?;
+            if paid > BLOCK_REWARD + fees {
+                return Err(invalid("coinbase pays more than the reward and fees"));
+            }
+            next.add_outputs(coinbase);
+        }
+
+        *self = next;
+        Ok(())
+    }
+
+    // Returns the fee
+    pub fn apply_transaction(&mut self, index: u64, transaction: &UtxoTransaction) -> Result<u64> {
+        let txid = transaction.txid();
+        if transaction.inputs.is_empty() {
+            return Err(BlockchainError::InvalidTransaction {
+                index,
+                message: format!("transaction {} spends nothing", txid),
+            });
+        }
+
+        let message = transaction.signing_message();
+        let mut inputs = 0u64;
+        for input in &transaction.inputs {
+            // Also catches the same output spent twice within the transaction
+            let spent = self
+                .unspent
+                .remove(input)
+                .ok_or_else(|| BlockchainError::DoubleSpend {
+                    output: input.to_string(),
+                })?;
+            if !wallet::verify(
+                &spent.owner,
+                &transaction.public_key,
+                &transaction.signature,
+                &message,
+            ) {
+                return Err(BlockchainError::InvalidSignature {
+                    index,
+                    signer: spent.owner,
+                });
+            }
+            inputs += spent.amount;
+        }
+        let outputs = total(&transaction.outputs, index)?;
+        if outputs > inputs {
+            return Err(BlockchainError::OutputsExceedInputs {
+                txid,
+                inputs,
+                outputs,
+            });
+        }
+
+        self.add_outputs(transaction);
+        Ok(inputs - outputs)
+    }
+
+    fn add_outputs(&mut self, transaction: &UtxoTransaction) {
+        let txid = transaction.txid();
+        for (index, output) in transaction.outputs.iter().enumerate() {
+            let outpoint = OutPoint {
+                txid: txid.clone(),
+                index: index as u32,
+            };
+            self.unspent.insert(outpoint, output.clone());
+        }
+    }
+
+    // Pays `amount` from `sender`'s outputs in txid order, with the change
+    // going back to the sender. Still needs signing.
+    pub fn transfer(&self, sender: &str, receiver: &str, amount: u64) -> Result<UtxoTransaction> {
+        if !wallet::is_address(receiver) {
+            return Err(BlockchainError::WalletError(format!(
+                "{} is not an address",
+                receiver
+            )));
+        }
+        let mut inputs = Vec::new();
+        let mut gathered = 0u64;
+        for (outpoint, output) in self.unspent.iter().filter(|(_, o)| o.owner == sender) {
+            if gathered >= amount {
+                break;
+            }
+            inputs.push(outpoint.clone());
+            gathered += output.amount;
+        }
+        if gathered < amount {
+            return Err(BlockchainError::InsufficientFunds {
+                account: sender.to_string(),
+                balance: gathered,
+                amount,
+            });
+        }
+
+        let mut outputs = vec![TxOutput {
+            owner: receiver.to_string(),
+            amount,
+        }];
+        if gathered > amount {
+            outputs.push(TxOutput {
+                owner: sender.to_string(),
+                amount: gathered - amount,
+            });
+        }
+        Ok(UtxoTransaction {
+            inputs,
+            outputs,
+            coinbase: None,
+            public_key: String::new(),
+            signature: String::new(),
+        })
+    }
+
+    // Commits to the whole set; stored in every block of a UTXO chain
+    pub fn root(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (outpoint, output) in &self.unspent {
+            hasher.update(
+                format!(
+                    "{}:{}>{}:{};",
+                    outpoint.txid, outpoint.index, output.owner, output.amount
+                )
+                .as_bytes(),
+            );
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    // Total and number of unspent outputs per owner, sorted by owner
+    pub fn balances(&self) -> BTreeMap<&str, (u64, usize)> {
+        let mut balances = BTreeMap::new();
+        for output in self.unspent.values() {
+            let (total, count) = balances.entry(output.owner.as_str()).or_insert((0, 0));
+            *total += output.amount;
+            *count += 1;
+        }
+        balances
+    }
+}
+
+fn total(outputs: &[TxOutput], index: u64) -> Result<u64> {
+    outputs.iter().try_fold(0u64, |sum, output| {
+        if output.amount == 0 {
+            return Err(BlockchainError::InvalidTransaction {
+                index,
+                message: "outputs must be positive".to_string(),
+            });
+        }
+        sum.checked_add(output.amount)
+            .ok_or_else(|| BlockchainError::InvalidTransaction {
+                index,
+                message: "outputs overflow".to_string(),
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(index: u64, utxo_transactions: Vec<UtxoTransaction>) -> Block {
+        Block {
+            index,
+            utxo_transactions,
+            ..Block::default()
+        }
+    }
+
+    // A set where `wallet` owns the reward of block 1
+    fn funded(wallet: &Wallet) -> UtxoSet {
+        let mut utxos = UtxoSet::default();
+        let coinbase = UtxoTransaction::coinbase(wallet.address(), 1);
+        utxos.apply_block(&block(1, vec![coinbase])).unwrap();
+        utxos
+    }
+
+    fn payment(utxos: &UtxoSet, wallet: &Wallet, receiver: &str, amount: u64) -> UtxoTransaction {
+        let mut transaction = utxos.transfer(&wallet.address(), receiver, amount).unwrap();
+        transaction.sign(wallet);
+        transaction
+    }
+
+    #[test]
+    fn transfers_pay_the_receiver_and_return_change() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut utxos = funded(&alice);
+        let transaction = payment(&utxos, &alice, &bob.address(), 20);
+        utxos.apply_block(&block(2, vec![transaction])).unwrap();
+
+        let balances = utxos.balances();
+        assert_eq!(balances[bob.address().as_str()], (20, 1));
+        assert_eq!(balances[alice.address().as_str()], (BLOCK_REWARD - 20, 1));
+    }
+
+    #[test]
+    fn an_output_cant_be_spent_twice_in_one_block() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let carol = Wallet::generate("carol".to_string());
+        let mut utxos = funded(&alice);
+        let first = payment(&utxos, &alice, &bob.address(), 20);
+        let second = payment(&utxos, &alice, &carol.address(), 20);
+        let result = utxos.apply_block(&block(2, vec![first, second]));
+        assert!(matches!(result, Err(BlockchainError::DoubleSpend { .. })));
+    }
+
+    #[test]
+    fn an_output_cant_be_spent_again_in_a_later_block() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut utxos = funded(&alice);
+        let transaction = payment(&utxos, &alice, &bob.address(), 20);
+        utxos
+            .apply_block(&block(2, vec![transaction.clone()]))
+            .unwrap();
+
+        let result = utxos.apply_block(&block(3, vec![transaction]));
+        assert!(matches!(result, Err(BlockchainError::DoubleSpend { .. })));
+    }
+
+    #[test]
+    fn outputs_cant_exceed_inputs() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut utxos = funded(&alice);
+        let mut transaction = utxos
+            .transfer(&alice.address(), &bob.address(), 20)
+            .unwrap();
+        transaction.outputs[0].amount = BLOCK_REWARD + 1;
+        transaction.sign(&alice);
+
+        let result = utxos.apply_block(&block(2, vec![transaction]));
+        assert!(matches!(
+            result,
+            Err(BlockchainError::OutputsExceedInputs {
+                inputs: BLOCK_REWARD,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn the_coinbase_must_come_first() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let miner = Wallet::generate("miner".to_string());
+        let mut utxos = funded(&alice);
+        let transactions = vec![
+            payment(&utxos, &alice, &bob.address(), 20),
+            UtxoTransaction::coinbase(miner.address(), 2),
+        ];
+        let result = utxos.apply_block(&block(2, transactions));
+        assert!(matches!(
+            result,
+            Err(BlockchainError::InvalidTransaction { index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn the_coinbase_may_claim_fees_but_no_more() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let miner = Wallet::generate("miner".to_string());
+        let utxos = funded(&alice);
+        // Pays 20 to bob and leaves a fee of 5 out of the change
+        let mut transaction = utxos
+            .transfer(&alice.address(), &bob.address(), 20)
+            .unwrap();
+        transaction.outputs[1].amount -= 5;
+        transaction.sign(&alice);
+
+        let mut coinbase = UtxoTransaction::coinbase(miner.address(), 2);
+        coinbase.outputs[0].amount = BLOCK_REWARD + 5;
+        let mut with_fees = utxos.clone();
+        with_fees
+            .apply_block(&block(2, vec![coinbase.clone(), transaction.clone()]))
+            .unwrap();
+        assert_eq!(
+            with_fees.balances()[miner.address().as_str()],
+            (BLOCK_REWARD + 5, 1)
+        );
+
+        coinbase.outputs[0].amount += 1;
+        let result = utxos
+            .clone()
+            .apply_block(&block(2, vec![coinbase, transaction]));
+        assert!(matches!(
+            result,
+            Err(BlockchainError::InvalidTransaction { index: 2, .. })
+        ));
+    }
+
+    // Blocks 0..=2 that commit to their UTXO roots, with `wallet` paid in block 1
+    fn committed_chain(wallet: &Wallet) -> Vec<Block> {
+        let mut utxos = UtxoSet::default();
+        let mut chain = vec![block(0, Vec::new())];
+        chain[0].utxo_root = utxos.root();
+        for index in 1..=2 {
+            let mut next = block(
+                index,
+                vec![UtxoTransaction::coinbase(wallet.address(), index)],
+            );
+            utxos.apply_block(&next).unwrap();
+            next.utxo_root = utxos.root();
+            next.hash = next.calculate_hash();
+            chain.push(next);
+        }
+        chain
+    }
+
+    #[test]
+    fn snapshots_are_only_trusted_with_our_own_checkpoint() {
+        let alice = Wallet::generate("alice".to_string());
+        let chain = committed_chain(&alice);
+        let at_block_1 = UtxoSet::from_chain(&chain[..2]).unwrap();
+        let snapshot = at_block_1.snapshot(1);
+
+        // The block commits to it, but that alone proves nothing
+        let mut checkpoints = Checkpoints::default();
+        let restored = UtxoSet::from_snapshot(snapshot.clone(), &chain, &checkpoints).unwrap();
+        assert!(restored.is_none());
+
+        checkpoints.record(&chain[1].hash, at_block_1.root());
+        let restored = UtxoSet::from_snapshot(snapshot.clone(), &chain, &checkpoints)
+            .unwrap()
+            .expect("checkpointed snapshot");
+        assert_eq!(restored.root(), chain[2].utxo_root);
+
+        // Edited snapshots don't match the pinned root
+        let mut forged = snapshot;
+        forged.unspent[0].1.owner = "mallory".to_string();
+        let restored = UtxoSet::from_snapshot(forged, &chain, &checkpoints).unwrap();
+        assert!(restored.is_none());
+    }
+
+    #[test]
+    fn inputs_must_be_signed_by_their_owner() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mallory = Wallet::generate("mallory".to_string());
+        let mut utxos = funded(&alice);
+
+        let mut tampered = payment(&utxos, &alice, &bob.address(), 20);
+        tampered.outputs[0].owner = mallory.address();
+        let mut forged = utxos
+            .transfer(&alice.address(), &mallory.address(), 20)
+            .unwrap();
+        forged.sign(&mallory);
+        for transaction in [tampered, forged] {
+            let result = utxos.apply_block(&block(2, vec![transaction]));
+            assert!(matches!(
+                result,
+                Err(BlockchainError::InvalidSignature { index: 2, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn transfers_to_anything_but_an_address_are_refused() {
+        let alice = Wallet::generate("alice".to_string());
+        let utxos = funded(&alice);
+        for receiver in ["bob", "", "not an address at all but forty chars!!"] {
+            let result = utxos.transfer(&alice.address(), receiver, 20);
+            assert!(matches!(result, Err(BlockchainError::WalletError(_))));
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{BlockchainError, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+pub const KEYSTORE_FILE: &str = "wallets.json";
+
+// Hex digits of the public key hash that make up an address
+const ADDRESS_LENGTH: usize = 40;
+
+// The address coins are sent to: a hash of the public key, so the key itself
+// only has to be revealed when spending
+pub fn address(public_key: &VerifyingKey) -> String {
+    let hash = format!("{:x}", Sha256::digest(public_key.as_bytes()));
+    hash[..ADDRESS_LENGTH].to_string()
+}
+
+// True if `text` has the shape of an address: lowercase hex of the right
+// length. Whether anyone holds the key is another matter.
+pub fn is_address(text: &str) -> bool {
+    text.len() == ADDRESS_LENGTH && text.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// True if `public_key` belongs to `owner` and signed `message`
+pub fn verify(owner: &str, public_key: &str, signature: &str, message: &str) -> bool {
+    let Some(public_key) = decode::<32>(public_key).and_then(|k| VerifyingKey::from_bytes(&k).ok())
+    else {
+        return false;
+    };
+    let Some(signature) = decode::<64>(signature).map(|s| Signature::from_bytes(&s)) else {
+        return false;
+    };
+    address(&public_key) == owner
+        && public_key
+            .verify_strict(message.as_bytes(), &signature)
+            .is_ok()
+}
+
+fn decode<const N: usize>(text: &str) -> Option<[u8; N]> {
+    hex::decode(text).ok()?.try_into().ok()
+}
+
+pub struct Wallet {
+    pub name: String,
+    signing_key: SigningKey,
+}
+
+impl Wallet {
+    pub fn generate(name: String) -> Self {
+        Self {
+            name,
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    pub fn address(&self) -> String {
+        address(&self.signing_key.verifying_key())
+    }
+
+    pub fn public_key(&self) -> String {
+        hex::encode(self.signing_key.verifying_key().as_bytes())
+    }
+
+    pub fn sign(&self, message: &str) -> String {
+        hex::encode(self.signing_key.sign(message.as_bytes()).to_bytes())
+    }
+}
+
+// A secret key encrypted with ChaCha20-Poly1305 under a key stretched from
+// the password with Argon2
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeystoreEntry {
+    pub name: String,
+    pub address: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Keystore {
+    wallets: Vec<KeystoreEntry>,
+}
+
+impl Keystore {
+    // An empty keystore if the file doesn't exist yet
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    // Written beside the old file and renamed over it, so a crash halfway
+    // through leaves the previous keys intact rather than a truncated file
+    pub fn save(&self, path: &str) -> Result<()> {
+        let temp = format!("{}.tmp", path);
+        fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, wallet: &Wallet, password: &str) -> Result<()> {
+        if self.find(&wallet.name).is_some() {
+            return Err(BlockchainError::WalletError(format!(
+                "a wallet named {} already exists",
+                wallet.name
+            )));
+        }
+
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let cipher = cipher(password, &salt)?;
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                wallet.signing_key.as_bytes().as_slice(),
+            )
+            .map_err(|_| BlockchainError::WalletError("encryption failed".to_string()))?;
+
+        self.wallets.push(KeystoreEntry {
+            name: wallet.name.clone(),
+            address: wallet.address(),
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        });
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&KeystoreEntry> {
+        self.wallets.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn name_of(&self, address: &str) -> Option<&str> {
+        self.wallets
+            .iter()
+            .find(|entry| entry.address == address)
+            .map(|entry| entry.name.as_str())
+    }
+
+    // A wallet name resolves to its address and an address stands for
+    // itself. Anything else is refused, since coins sent to a typo could
+    // never be spent.
+    pub fn resolve(&self, name_or_address: &str) -> Result<String> {
+        if let Some(entry) = self.find(name_or_address) {
+            return Ok(entry.address.clone());
+        }
+        if is_address(name_or_address) {
+            return Ok(name_or_address.to_string());
+        }
+        Err(BlockchainError::WalletError(format!(
+            "{} is neither a wallet nor an address",
+            name_or_address
+        )))
+    }
+
+    pub fn unlock(&self, name: &str, password: &str) -> Result<Wallet> {
+        let entry = self
+            .find(name)
+            .ok_or_else(|| BlockchainError::WalletError(format!("no wallet named {}", name)))?;
+        let corrupt =
+            || BlockchainError::WalletError(format!("keystore entry {} is corrupt", name));
+        let salt = hex::decode(&entry.salt).map_err(|_| corrupt())?;
+        let nonce = decode::<12>(&entry.nonce).ok_or_else(corrupt)?;
+        let ciphertext = hex::decode(&entry.ciphertext).map_err(|_| corrupt())?;
+
+        let secret = cipher(password, &salt)?
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| BlockchainError::WalletError("wrong password".to_string()))?;
+        let secret: [u8; 32] = secret.try_into().map_err(|_| corrupt())?;
+        let wallet = Wallet {
+            name: entry.name.clone(),
+            signing_key: SigningKey::from_bytes(&secret),
+        };
+        if wallet.address() != entry.address {
+            return Err(corrupt());
+        }
+        Ok(wallet)
+    }
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| BlockchainError::WalletError(format!("key derivation failed: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify_only_for_the_signed_message_and_key() {
+        let alice = Wallet::generate("alice".to_string());
+        let mallory = Wallet::generate("mallory".to_string());
+        let signature = alice.sign("pay bob 10");
+
+        assert!(verify(
+            &alice.address(),
+            &alice.public_key(),
+            &signature,
+            "pay bob 10"
+        ));
+        assert!(!verify(
+            &alice.address(),
+            &alice.public_key(),
+            &signature,
+            "pay bob 100"
+        ));
+        // Mallory's key doesn't belong to Alice's address, and didn't sign
+        assert!(!verify(
+            &alice.address(),
+            &mallory.public_key(),
+            &signature,
+            "pay bob 10"
+        ));
+        let forged = mallory.sign("pay bob 10");
+        assert!(!verify(
+            &alice.address(),
+            &mallory.public_key(),
+            &forged,
+            "pay bob 10"
+        ));
+        assert!(!verify(
+            &alice.address(),
+            "not hex",
+            &signature,
+            "pay bob 10"
+        ));
+    }
+
+    #[test]
+    fn keystore_round_trips_through_its_file() {
+        let path = std::env::temp_dir().join(format!("wallets-test-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let alice = Wallet::generate("alice".to_string());
+        let mut keystore = Keystore::default();
+        keystore.add(&alice, "correct horse").unwrap();
+        keystore.save(path).unwrap();
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+
+        let loaded = Keystore::load(path);
+        fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+        let unlocked = loaded.unlock("alice", "correct horse").unwrap();
+        assert_eq!(unlocked.address(), alice.address());
+        assert_eq!(loaded.resolve("alice").unwrap(), alice.address());
+        assert_eq!(loaded.name_of(&alice.address()), Some("alice"));
+    }
+
+    #[test]
+    fn the_wrong_password_doesnt_unlock() {
+        let alice = Wallet::generate("alice".to_string());
+        let mut keystore = Keystore::default();
+        keystore.add(&alice, "correct horse").unwrap();
+        assert!(matches!(
+            keystore.unlock("alice", "battery staple"),
+            Err(BlockchainError::WalletError(_))
+        ));
+    }
+
+    #[test]
+    fn wallet_names_are_unique() {
+        let mut keystore = Keystore::default();
+        keystore
+            .add(&Wallet::generate("alice".to_string()), "one")
+            .unwrap();
+        let result = keystore.add(&Wallet::generate("alice".to_string()), "two");
+        assert!(matches!(result, Err(BlockchainError::WalletError(_))));
+    }
+
+    #[test]
+    fn unreadable_keystores_are_an_error() {
+        let path = std::env::temp_dir().join(format!("wallets-bad-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "{not json").unwrap();
+        let loaded = Keystore::load(path);
+        fs::remove_file(path).unwrap();
+        assert!(matches!(loaded, Err(BlockchainError::JsonError(_))));
+    }
+
+    #[test]
+    fn only_known_wallets_and_addresses_resolve() {
+        let alice = Wallet::generate("alice".to_string());
+        let bob = Wallet::generate("bob".to_string());
+        let mut keystore = Keystore::default();
+        keystore.add(&alice, "correct horse").unwrap();
+
+        assert_eq!(keystore.resolve("alice").unwrap(), alice.address());
+        assert_eq!(keystore.resolve(&bob.address()).unwrap(), bob.address());
+        for typo in [
+            "alcie",
+            "bob",
+            "",
+            &bob.address()[1..],
+            &bob.address().to_uppercase(),
+        ] {
+            assert!(
+                matches!(keystore.resolve(typo), Err(BlockchainError::WalletError(_))),
+                "{} resolved",
+                typo
+            );
+        }
+    }
+}